@@ -4,6 +4,8 @@
 Example daemon script to communicate with the rust application
 """
 
+import json
+import os
 import zmq
 import sys
 
@@ -11,6 +13,21 @@ import sys
 PROTOCOL = "tcp"
 HOST = "127.0.0.1"
 PORT = 5747
+# Overrides the above, e.g. STATUS_ENDPOINT=ipc:///run/status.sock
+ENDPOINT = os.environ.get("STATUS_ENDPOINT", f"{PROTOCOL}://{HOST}:{PORT}")
+
+# CURVE keys, only needed if the server has "curve" enabled
+# STATUS_SERVER_KEY is the server.pub file, STATUS_CLIENT_KEY the prefix of the client.key/client.pub files from `status keygen`
+SERVER_KEY = os.environ.get("STATUS_SERVER_KEY")
+CLIENT_KEY = os.environ.get("STATUS_CLIENT_KEY")
+
+# Shared token identifying this client, only needed if the server has "access" enabled
+TOKEN = os.environ.get("STATUS_TOKEN")
+
+
+def read_key(path):
+    with open(path, "rb") as f:
+        return f.read().strip()
 
 
 
@@ -27,9 +44,15 @@ context = zmq.Context()
 
 # Socket to talk to server
 socket = context.socket(zmq.DEALER)
-socket.connect(f"{PROTOCOL}://{HOST}:{PORT}")
+if SERVER_KEY and CLIENT_KEY:
+    socket.curve_serverkey = read_key(SERVER_KEY)
+    socket.curve_publickey = read_key(f"{CLIENT_KEY}.pub")
+    socket.curve_secretkey = read_key(f"{CLIENT_KEY}.key")
+socket.connect(ENDPOINT)
 
 # Send request
+if TOKEN:
+    argz = json.dumps({"command": argz, "token": TOKEN})
 socket.send(argz.encode("UTF-8"))
 
 # Get reply
@@ -0,0 +1,30 @@
+use chrono::prelude::*;
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use serde::{Deserialize, Serialize};
+
+/// A problem with a service someone knows about and is working on, cleared once the service recovered from it.
+/// Only kept in memory, like the rest of the state of the services it's gone after a restart.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Acknowledgement {
+    /// The client that acknowledged the service
+    pub by: String,
+    #[serde(with = "ts_seconds")]
+    pub at: DateTime<Utc>,
+    pub comment: Option<String>,
+    /// Cleared at this time even if the service is still failing, only cleared by the recovery if `None`
+    #[serde(with = "ts_seconds_option")]
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// A service that shouldn't get anyone's attention for a while, whether it's failing or not.
+/// Only kept in memory, like `Acknowledgement`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Silence {
+    /// The client that silenced the service
+    pub by: String,
+    #[serde(with = "ts_seconds")]
+    pub at: DateTime<Utc>,
+    pub comment: Option<String>,
+    #[serde(with = "ts_seconds")]
+    pub until: DateTime<Utc>,
+}
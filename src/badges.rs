@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::history::History;
+use crate::status_page::PublicService;
+use crate::utils::html::escape;
+use crate::utils::status::Status;
+
+/// Where the SVG badges of the services are written to
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BadgeSettings {
+    pub directory: String,
+    /// How many days the uptime on the badges goes back
+    pub days: u32,
+}
+
+impl BadgeSettings {
+    /// Parses the `badges` object of the settings, e.g. `{"directory": "/var/www/badges", "days": 30}`
+    pub fn from_value(value: &Value) -> Option<BadgeSettings> {
+        Some(BadgeSettings {
+            directory: value.get("directory")?.as_str()?.to_string(),
+            days: value
+                .get("days")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .unwrap_or(30),
+        })
+    }
+}
+
+/// Writes the badge of a service to `<name>.svg` in the badge directory, e.g. "website_1 | up 99.9%".
+///
+/// Like on the status page, only public services get a badge (see `PublicService`), and the public name is used if set.
+/// The state comes from the status of the service, the percentage from the uptime in the history the way the `report`
+/// command calculates it (left out without one), and the colour from the current success score.
+///
+/// # Arguments
+///
+/// * `badges` - The badge settings.
+/// * `service` - The service to write the badge of.
+/// * `history` - The run history, for the uptime.
+pub fn write(badges: &BadgeSettings, service: &PublicService, history: &History) -> io::Result<()> {
+    let name = service.public.name.as_deref().unwrap_or(&service.name);
+    let state = match service.status {
+        Status::Ok => "up",
+        Status::Warning => "degraded",
+        Status::Critical => "down",
+        Status::Unknown => "unknown",
+        Status::Pending => "pending",
+        Status::Skipped => "skipped",
+    };
+    let message = match history.report_uptime(&service.name, service.interval, service.partial_success, badges.days) {
+        Some(uptime) => format!("{} {}", state, percentage(uptime)),
+        None => state.to_string(),
+    };
+    let color = match service.status {
+        Status::Unknown | Status::Pending | Status::Skipped => "#9f9f9f",
+        _ => match service.thresholds.status_of(service.successes) {
+            Status::Ok => "#4c1",
+            Status::Warning => "#dfb317",
+            _ => "#e05d44",
+        },
+    };
+
+    fs::create_dir_all(&badges.directory)?;
+    let file = format!("{}.svg", file_name(name));
+    let temporary = Path::new(&badges.directory).join(format!(".{}.tmp", file));
+    fs::write(&temporary, svg(name, &message, color))?;
+    fs::rename(&temporary, Path::new(&badges.directory).join(file))
+}
+
+/// A flat badge in the style of shields.io, the label on grey and the message on the colour
+fn svg(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label) + 10;
+    let message_width = text_width(message) + 10;
+    let width = label_width + message_width;
+    let (label, message) = (escape(label), escape(message));
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"20\" role=\"img\" aria-label=\"{label}: {message}\">\
+         <title>{label}: {message}</title>\
+         <linearGradient id=\"s\" x2=\"0\" y2=\"100%\"><stop offset=\"0\" stop-color=\"#bbb\" stop-opacity=\".1\"/><stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>\
+         <clipPath id=\"r\"><rect width=\"{width}\" height=\"20\" rx=\"3\" fill=\"#fff\"/></clipPath>\
+         <g clip-path=\"url(#r)\"><rect width=\"{label_width}\" height=\"20\" fill=\"#555\"/>\
+         <rect x=\"{label_width}\" width=\"{message_width}\" height=\"20\" fill=\"{color}\"/>\
+         <rect width=\"{width}\" height=\"20\" fill=\"url(#s)\"/></g>\
+         <g fill=\"#fff\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\
+         <text x=\"{label_x}\" y=\"15\" fill=\"#010101\" fill-opacity=\".3\">{label}</text><text x=\"{label_x}\" y=\"14\">{label}</text>\
+         <text x=\"{message_x}\" y=\"15\" fill=\"#010101\" fill-opacity=\".3\">{message}</text><text x=\"{message_x}\" y=\"14\">{message}</text></g></svg>\n",
+        label_x = label_width as f64 / 2.0,
+        message_x = label_width as f64 + message_width as f64 / 2.0,
+    )
+}
+
+/// Roughly how wide a text is in 11px Verdana, there is no font to measure it with
+fn text_width(text: &str) -> u32 {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'l' | 'j' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' => 4,
+            'f' | 't' | 'r' | ' ' | '(' | ')' | '[' | ']' | '-' => 5,
+            'm' | 'w' | 'M' | 'W' | '%' => 11,
+            c if c.is_uppercase() || c.is_ascii_digit() => 8,
+            _ => 7,
+        })
+        .sum()
+}
+
+/// An uptime as a percentage with a single decimal, never rounded up to 100% unless it is
+fn percentage(uptime: f64) -> String {
+    if uptime >= 1.0 {
+        return "100%".to_string();
+    }
+    format!("{:.1}%", (uptime * 1000.0).floor() / 10.0)
+}
+
+/// The service name made safe to use as a file name, different names can end up with the same file name
+pub fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,284 @@
+use chrono::prelude::*;
+use chrono::serde::ts_seconds;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+
+use crate::report::{self, MAX_SPAN_INTERVALS};
+use crate::service::Service;
+use crate::settings::ResultOutput;
+use crate::utils::partial_success::PartialSuccess;
+use crate::utils::status::Status;
+
+/// Where and how long the results of every run are kept
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HistorySettings {
+    pub file: String,
+    /// Runs older than this are dropped when the daemon starts, and every day after that
+    pub retention_days: u32,
+}
+
+impl HistorySettings {
+    /// Parses the `history` object of the settings, e.g. `{"file": "/var/lib/status/history.jsonl", "retention_days": 400}`
+    pub fn from_value(value: &Value) -> Option<HistorySettings> {
+        Some(HistorySettings {
+            file: value.get("file")?.as_str()?.to_string(),
+            retention_days: value
+                .get("retention_days")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .unwrap_or(400),
+        })
+    }
+}
+
+/// A single test result of a run
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TestRecord {
+    pub name: String,
+    pub success: f64,
+    pub status: Option<Status>,
+}
+
+/// The outcome of a single run of a service, a line of the history file
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RunRecord {
+    #[serde(with = "ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    pub service: String,
+    pub status: Status,
+    pub successes: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestRecord>,
+}
+
+impl RunRecord {
+    /// The record of the last run of a service
+    pub fn from_service(service: &Service) -> RunRecord {
+        let tests = match &service.result {
+            ResultOutput::Result(results) => results
+                .iter()
+                .map(|test| TestRecord {
+                    name: test.name.clone(),
+                    success: test.success,
+                    status: test.status,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        RunRecord {
+            timestamp: service.last_run.unwrap_or_else(Utc::now),
+            service: service.name.clone(),
+            status: service.status,
+            successes: service.successes,
+            tests,
+        }
+    }
+
+    /// Whether the run counts towards the uptime. Skipped and unknown runs (e.g. a command that couldn't be started
+    /// or output that couldn't be parsed) don't, as they don't tell whether the service was up
+    pub fn counts(&self) -> bool {
+        !matches!(self.status, Status::Pending | Status::Skipped | Status::Unknown)
+    }
+}
+
+/// The runs of a service on a single day
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DailyUptime {
+    pub runs: u32,
+    /// The sum of the success scores of the runs
+    pub successes: f64,
+}
+
+impl DailyUptime {
+    /// The average success score of the runs of the day
+    pub fn uptime(&self) -> f64 {
+        self.successes / self.runs as f64
+    }
+}
+
+/// The run history of every service, appended to a JSON Lines file.
+///
+/// Only the daily averages are kept in memory, the file itself is read when the runs are needed.
+pub struct History {
+    settings: Option<HistorySettings>,
+    daily: HashMap<String, BTreeMap<NaiveDate, DailyUptime>>,
+    /// The day the runs older than the retention were last dropped
+    pruned_on: NaiveDate,
+}
+
+impl History {
+    /// Loads the history file, dropping the runs older than the retention.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The history settings, nothing is kept if `None`.
+    pub fn load(settings: Option<HistorySettings>) -> History {
+        let mut history = History {
+            settings,
+            daily: HashMap::new(),
+            pruned_on: Utc::now().date_naive(),
+        };
+        for record in &history.prune() {
+            history.add_daily(record);
+        }
+        history
+    }
+
+    /// Drops the runs older than the retention from the history file and the daily averages.
+    ///
+    /// # Returns
+    ///
+    /// * The runs that are kept, in the order they ran.
+    fn prune(&mut self) -> Vec<RunRecord> {
+        let Some(settings) = &self.settings else {
+            return vec![];
+        };
+        let cutoff = Utc::now() - Duration::days(settings.retention_days as i64);
+        let records = match read(&settings.file, None, None) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("Unable to read the history {}: {}", settings.file, e);
+                return vec![];
+            }
+        };
+        let total = records.len();
+        let kept: Vec<RunRecord> = records.into_iter().filter(|r| r.timestamp >= cutoff).collect();
+        if kept.len() < total
+            && let Err(e) = rewrite(&settings.file, &kept)
+        {
+            eprintln!("Unable to drop old runs from the history {}: {}", settings.file, e);
+        }
+        for daily in self.daily.values_mut() {
+            daily.retain(|date, _| *date >= cutoff.date_naive());
+        }
+        kept
+    }
+
+    /// Records the last run of a service, appending it to the history file.
+    /// The first run of a day also drops the runs older than the retention.
+    pub fn record(&mut self, service: &Service) {
+        let today = Utc::now().date_naive();
+        if self.pruned_on != today {
+            self.pruned_on = today;
+            self.prune();
+        }
+        let Some(settings) = &self.settings else {
+            return;
+        };
+        let record = RunRecord::from_service(service);
+        if let Err(e) = append(&settings.file, &record) {
+            eprintln!("Unable to write to the history {}: {}", settings.file, e);
+        }
+        self.add_daily(&record);
+    }
+
+    /// The daily uptime of a service over the last days, the oldest first, `None` for days without runs
+    pub fn daily(&self, service: &str, days: u32) -> Vec<(NaiveDate, Option<DailyUptime>)> {
+        let today = Utc::now().date_naive();
+        let daily = self.daily.get(service);
+        (0..days as i64)
+            .rev()
+            .map(|ago| {
+                let date = today - Duration::days(ago);
+                (date, daily.and_then(|d| d.get(&date)).copied())
+            })
+            .collect()
+    }
+
+    /// The average uptime of a service over the last days, `None` if it didn't run
+    pub fn uptime(&self, service: &str, days: u32) -> Option<f64> {
+        let (runs, successes) = self
+            .daily(service, days)
+            .iter()
+            .filter_map(|(_, day)| *day)
+            .fold((0, 0.0), |(runs, successes), day| (runs + day.runs, successes + day.successes));
+        if runs == 0 { None } else { Some(successes / runs as f64) }
+    }
+
+    /// The uptime of a service over the last days the way the `report` command calculates it, weighted by time and
+    /// respecting `partial_success` and gaps. Reads the history file, `None` without one or if the service didn't run.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The name of the service.
+    /// * `interval` - The interval of the service in seconds.
+    /// * `partial_success` - How runs that only partly succeeded count towards the uptime.
+    /// * `days` - How many days the uptime goes back.
+    pub fn report_uptime(&self, service: &str, interval: u64, partial_success: PartialSuccess, days: u32) -> Option<f64> {
+        let settings = self.settings.as_ref()?;
+        let to = Utc::now();
+        let from = to - Duration::days(days as i64);
+        // Runs from further back than this can't hold into the period
+        let since = from - Duration::seconds(interval as i64 * MAX_SPAN_INTERVALS);
+        let records = match read(&settings.file, Some(since), None) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("Unable to read the history {}: {}", settings.file, e);
+                return None;
+            }
+        };
+        report::report(service, &records, interval, from, to, partial_success).uptime
+    }
+
+    fn add_daily(&mut self, record: &RunRecord) {
+        if !record.counts() {
+            return;
+        }
+        let day = self
+            .daily
+            .entry(record.service.clone())
+            .or_default()
+            .entry(record.timestamp.date_naive())
+            .or_default();
+        day.runs += 1;
+        day.successes += record.successes;
+    }
+}
+
+/// Reads the runs of the history file between two points in time, in the order they ran.
+/// Lines that can't be parsed are skipped.
+pub fn read(path: &str, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> io::Result<Vec<RunRecord>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut records = vec![];
+    for line in BufReader::new(file).lines() {
+        let Ok(record) = serde_json::from_str::<RunRecord>(&line?) else {
+            continue;
+        };
+        if since.is_some_and(|since| record.timestamp < since) || until.is_some_and(|until| record.timestamp > until) {
+            continue;
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn append(path: &str, record: &RunRecord) -> io::Result<()> {
+    let mut line = serde_json::to_string(record).map_err(io::Error::other)?;
+    line.push('\n');
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Replaces the history file with the given runs, through a temporary file so it's never left half written
+fn rewrite(path: &str, records: &[RunRecord]) -> io::Result<()> {
+    let temporary = format!("{}.tmp", path);
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&serde_json::to_string(record).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path)
+}
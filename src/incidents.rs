@@ -0,0 +1,193 @@
+use chrono::prelude::*;
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::utils::ring_buffer::RingBuffer;
+use crate::utils::status::Status;
+
+/// How much of the output of the latest failing run is kept on an incident
+const OUTPUT_BYTES: usize = 4096;
+
+/// How many incidents are kept, the oldest resolved ones are dropped past this
+const MAX_INCIDENTS: usize = 1000;
+
+/// A note attached to an incident, e.g. by whoever is on call
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Note {
+    #[serde(with = "ts_seconds")]
+    pub at: DateTime<Utc>,
+    /// The client that attached the note
+    pub author: String,
+    pub text: String,
+}
+
+/// A period a service was down, from its first critical run until it recovered
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Incident {
+    pub id: u64,
+    pub service: String,
+    #[serde(with = "ts_seconds")]
+    pub started_at: DateTime<Utc>,
+    /// When the last critical run of the incident was
+    #[serde(with = "ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+    /// `None` while the service is still down
+    #[serde(with = "ts_seconds_option")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// The critical runs of the incident
+    pub failures: u64,
+    /// The tests that were failing when the incident was opened
+    pub failing_tests: Vec<TestResult>,
+    /// The (truncated) output of the latest critical run
+    pub latest_output: String,
+    pub notes: Vec<Note>,
+}
+
+impl Incident {
+    pub fn is_open(&self) -> bool {
+        self.resolved_at.is_none()
+    }
+}
+
+/// Every incident, opened and resolved as the services go down and recover
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Incidents {
+    /// The file the incidents are kept in, only kept in memory if `None`
+    #[serde(skip)]
+    file: Option<String>,
+    pub incidents: Vec<Incident>,
+}
+
+impl Incidents {
+    /// Loads the incidents from a file, starting without any if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The file the incidents are kept in, only kept in memory if `None`.
+    pub fn load(file: Option<String>) -> Incidents {
+        let incidents = match &file {
+            Some(file) => match fs::read_to_string(file) {
+                Ok(content) => serde_json::from_str::<Vec<Incident>>(&content).unwrap_or_else(|e| {
+                    eprintln!("Unable to parse the incidents in {}, starting without any: {}", file, e);
+                    vec![]
+                }),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+                Err(e) => {
+                    eprintln!("Unable to read the incidents in {}, starting without any: {}", file, e);
+                    vec![]
+                }
+            },
+            None => vec![],
+        };
+        Incidents { file, incidents }
+    }
+
+    /// Opens, updates or resolves the incident of a service after it ran.
+    ///
+    /// A critical run opens an incident, or updates the open one. The first ok or warning run resolves it,
+    /// unknown and skipped runs leave it open as it isn't known whether the service recovered.
+    pub fn update(&mut self, service: &Service) {
+        let now = service.last_run.unwrap_or_else(Utc::now);
+        let open = self
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.service == service.name && incident.is_open());
+
+        match (service.status, open) {
+            (Status::Critical, Some(incident)) => {
+                incident.updated_at = now;
+                incident.failures += 1;
+                incident.latest_output = latest_output(service);
+            }
+            (Status::Critical, None) => {
+                let failing_tests = match &service.result {
+                    ResultOutput::Result(results) => results
+                        .iter()
+                        .filter(|r| r.status.is_some_and(|s| s.is_problem()))
+                        .cloned()
+                        .collect(),
+                    _ => vec![],
+                };
+                let id = self.incidents.iter().map(|i| i.id).max().unwrap_or(0) + 1;
+                eprintln!("!  Opened incident #{} for {}", id, service.name);
+                self.drop_oldest_resolved();
+                self.incidents.push(Incident {
+                    id,
+                    service: service.name.clone(),
+                    started_at: now,
+                    updated_at: now,
+                    resolved_at: None,
+                    failures: 1,
+                    failing_tests,
+                    latest_output: latest_output(service),
+                    notes: vec![],
+                });
+            }
+            (status, Some(incident)) if status.is_recovered() => {
+                eprintln!("!  Resolved incident #{} for {}", incident.id, service.name);
+                incident.resolved_at = Some(now);
+            }
+            _ => return,
+        }
+        self.save();
+    }
+
+    /// Makes room for a new incident by dropping the oldest resolved ones, open incidents are always kept
+    fn drop_oldest_resolved(&mut self) {
+        let mut excess = (self.incidents.len() + 1).saturating_sub(MAX_INCIDENTS);
+        self.incidents.retain(|incident| {
+            if excess > 0 && !incident.is_open() {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Attaches a note to an incident, returning what was done
+    pub fn note(&mut self, id: u64, author: &str, text: String) -> Result<String, String> {
+        let incident = self
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.id == id)
+            .ok_or(format!("No incident #{}", id))?;
+        incident.notes.push(Note {
+            at: Utc::now(),
+            author: author.to_string(),
+            text,
+        });
+        self.save();
+        Ok(format!("Added a note to incident #{}", id))
+    }
+
+    /// Writes the incidents to their file, through a temporary file so it's never left half written
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let temporary = format!("{}.tmp", file);
+        let result = serde_json::to_string_pretty(&self.incidents)
+            .map_err(io::Error::other)
+            .and_then(|content| fs::write(&temporary, content))
+            .and_then(|_| fs::rename(&temporary, file));
+        if let Err(e) = result {
+            eprintln!("Unable to write the incidents to {}: {}", file, e);
+        }
+    }
+}
+
+/// The end of the stdout and stderr of the last run, or its result if there is no raw output
+fn latest_output(service: &Service) -> String {
+    let output = match &service.raw_output {
+        Some(raw) if raw.stderr.trim().is_empty() => raw.stdout.clone(),
+        Some(raw) => format!("{}\n{}", raw.stdout, raw.stderr),
+        None => serde_json::to_string(&service.result).unwrap_or_default(),
+    };
+    let mut buffer = RingBuffer::new(OUTPUT_BYTES);
+    buffer.push(output.as_bytes());
+    buffer.into_string()
+}
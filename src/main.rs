@@ -1,57 +1,359 @@
-use clap::Parser;
-use online;
-use std::sync::{Arc, Mutex};
+use clap::{Parser, Subcommand};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
 use std::thread::JoinHandle;
-use std::{thread, time};
+use std::time::Instant;
+use std::{process, thread, time};
 use chrono::prelude::*;
 
+use crate::history::History;
+use crate::service::Service;
+use crate::incidents::Incidents;
 use crate::settings::{ResultOutput, Settings};
+use crate::status_page::{public_services, PublicService};
 use crate::tester::Tester;
+use crate::tui::ClientOptions;
+use crate::utils::status::Status;
+use crate::zmq_handler::curve::generate_keys;
 use crate::zmq_handler::ZmqHandler;
 
 // headers
+pub mod acknowledgement;
+pub mod badges;
+pub mod history;
+pub mod incidents;
+pub mod report;
 pub mod settings;
+pub mod status_page;
 pub mod tester;
+pub mod tui;
 pub mod utils;
+pub mod validation;
 pub mod zmq_handler;
 mod service;
 
+/// How long to wait before restarting a crashed listener, binding its endpoints again.
+/// Doubled every time it crashes again soon after, up to `LISTENER_MAX_RESTART_DELAY`.
+const LISTENER_RESTART_DELAY: time::Duration = time::Duration::from_secs(5);
+/// The longest wait before restarting a crashed listener, also how long it has to run to start over from `LISTENER_RESTART_DELAY`
+const LISTENER_MAX_RESTART_DELAY: time::Duration = time::Duration::from_secs(300);
+
 /// Status daemon written in rust.
 /// Check services output and communicate via named pipe
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// Path to json settings file
+    /// Path to the settings file (JSON, JSONC, YAML or TOML)
     settings: Option<String>,
+
+    /// Validate the settings file and exit, without starting the daemon
+    #[arg(long)]
+    check_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a CURVE key pair for the server or a client
+    Keygen {
+        /// Write the keys to <OUTPUT>.key and <OUTPUT>.pub instead of printing them
+        #[arg(long)]
+        output: Option<String>,
+    },
+
+    /// Show a live dashboard of the services of a running daemon
+    Tui {
+        /// The ZMQ endpoint of the daemon
+        #[arg(long, default_value = "tcp://127.0.0.1:5747")]
+        endpoint: String,
+
+        /// How often to refresh, in seconds
+        #[arg(long, default_value_t = 2)]
+        refresh: u64,
+
+        /// The file with the CURVE public key of the daemon, if it has "curve" enabled
+        #[arg(long)]
+        server_key: Option<String>,
+
+        /// The prefix of the CURVE key files of this client, from `status keygen --output`
+        #[arg(long)]
+        client_key: Option<String>,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let settings = Settings::new(cli.settings);
+    match cli.command {
+        Some(Command::Keygen { output }) => {
+            if let Err(e) = generate_keys(output) {
+                eprintln!("Unable to generate keys: {}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Tui {
+            endpoint,
+            refresh,
+            server_key,
+            client_key,
+        }) => {
+            // Read from the environment rather than an argument, to keep it out of the process list
+            let token = std::env::var("STATUS_TOKEN").ok();
+            let options = ClientOptions {
+                endpoint,
+                token,
+                server_key,
+                client_key,
+            };
+            if let Err(e) = tui::run(options, refresh) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let settings = match Settings::new(cli.settings) {
+        Ok((settings, warnings)) => {
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+            if cli.check_config {
+                println!("Configuration OK");
+                process::exit(0);
+            }
+            settings
+        }
+        Err(problems) => {
+            for problem in &problems {
+                eprintln!("{}", problem);
+            }
+            process::exit(1);
+        }
+    };
+    let history = History::load(settings.history.clone());
+    // So the page and badges are there before the first runs are done
+    let public_services = public_services(&settings.services);
+    if let Some(page) = &settings.status_page
+        && let Err(e) = status_page::render(page, &public_services, &history)
+    {
+        eprintln!("Unable to write the status page to {}: {}", page.directory, e);
+    }
+    if let Some(badges) = &settings.badges {
+        for service in &public_services {
+            if let Err(e) = badges::write(badges, service, &history) {
+                eprintln!("Unable to write the badge of {} to {}: {}", service.name, badges.directory, e);
+            }
+        }
+    }
+    let history_mutex = Arc::new(Mutex::new(history));
+    let incidents_mutex = Arc::new(Mutex::new(Incidents::load(settings.incidents_file.clone())));
     let settings_mutex = Arc::new(Mutex::new(settings));
-    let mut zmq_handler = ZmqHandler::new(Arc::clone(&settings_mutex));
+    // Wakes up the test loops when a service is changed through the control socket
+    let wakeup = Arc::new(Condvar::new());
+    // Binding here rather than in the thread, so the daemon doesn't start if the endpoints can't be bound
+    let zmq_handler = match ZmqHandler::new(Arc::clone(&settings_mutex), Arc::clone(&incidents_mutex), Arc::clone(&wakeup)) {
+        Ok(zmq_handler) => zmq_handler,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
 
     // Starting listening thread
-    thread::Builder::new()
-        .name("Listener".to_string())
-        .spawn(move || zmq_handler.listen())
-        .unwrap();
+    let listener = spawn_listener(
+        Arc::clone(&settings_mutex),
+        Arc::clone(&incidents_mutex),
+        Arc::clone(&wakeup),
+        Some(zmq_handler),
+    );
 
     // Setting up multithreading handles
     let mut handles: Vec<JoinHandle<()>> = vec![];
 
     // Looks a bit cryptic, this was needed to allow shared memory
     let services_mutex = Arc::clone(&settings_mutex);
-    for i in 0..services_mutex.lock().unwrap().services.len() {
-        let services_mutex = Arc::clone(&services_mutex);
-        let handle = thread::spawn(move || test_loop(services_mutex, i));
-        handles.push(handle);
+    let services_count = services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services.len();
+    for i in 0..services_count {
+        handles.push(spawn_test_loop(
+            Arc::clone(&services_mutex),
+            Arc::clone(&history_mutex),
+            Arc::clone(&incidents_mutex),
+            Arc::clone(&wakeup),
+            i,
+        ));
     }
 
-    // Joining the handles (starting the multithreading)
-    for handle in handles {
-        handle.join().unwrap();
+    // Keeping the listener and test loops alive
+    supervise(services_mutex, history_mutex, incidents_mutex, wakeup, listener, handles);
+}
+
+/// Spawns the thread listening for commands, which only finishes if its endpoints can't be bound again.
+///
+/// # Arguments
+///
+/// * `settings_mutex` - An Arc Mutex that contains the settings.
+/// * `incidents_mutex` - An Arc Mutex that contains the incidents.
+/// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
+/// * `zmq_handler` - The already bound handler, or `None` to bind the endpoints again in the new thread.
+fn spawn_listener(
+    settings_mutex: Arc<Mutex<Settings>>,
+    incidents_mutex: Arc<Mutex<Incidents>>,
+    wakeup: Arc<Condvar>,
+    zmq_handler: Option<ZmqHandler>,
+) -> JoinHandle<Result<(), String>> {
+    thread::Builder::new()
+        .name("Listener".to_string())
+        .spawn(move || {
+            let mut zmq_handler = match zmq_handler {
+                Some(zmq_handler) => zmq_handler,
+                None => ZmqHandler::new(settings_mutex, incidents_mutex, wakeup)?,
+            };
+            zmq_handler.listen();
+            Ok(())
+        })
+        .expect("Unable to spawn listener thread")
+}
+
+/// Spawns the thread running `test_loop` for a single service, named after the service.
+///
+/// # Arguments
+///
+/// * `services_mutex` - An Arc Mutex that contains the settings.
+/// * `history_mutex` - An Arc Mutex that contains the run history.
+/// * `incidents_mutex` - An Arc Mutex that contains the incidents.
+/// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
+/// * `index` - The index of the service to be tested.
+fn spawn_test_loop(
+    services_mutex: Arc<Mutex<Settings>>,
+    history_mutex: Arc<Mutex<History>>,
+    incidents_mutex: Arc<Mutex<Incidents>>,
+    wakeup: Arc<Condvar>,
+    index: usize,
+) -> JoinHandle<()> {
+    let name = services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services[index].name.clone();
+    thread::Builder::new()
+        .name(name)
+        .spawn(move || test_loop(services_mutex, history_mutex, incidents_mutex, wakeup, index))
+        .expect("Unable to spawn test loop thread")
+}
+
+/// Keeps an eye on the listener and the test loops, restarting any thread that died.
+/// Meanwhile the acknowledgements and silences are cleared once they run out, or the service recovered.
+///
+/// A crashed listener is restarted after `LISTENER_RESTART_DELAY`, binding its endpoints again,
+/// backing off while it keeps failing, e.g. because the port is still taken.
+/// A crashed test loop is reported, both in the log and as an unknown result on the service.
+/// It is restarted after the interval of the service, just like the next run would have happened if it didn't crash.
+///
+/// # Arguments
+///
+/// * `services_mutex` - An Arc Mutex that contains the settings.
+/// * `history_mutex` - An Arc Mutex that contains the run history.
+/// * `incidents_mutex` - An Arc Mutex that contains the incidents.
+/// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
+/// * `listener` - The handle of the listener thread.
+/// * `handles` - The handles of the test loop threads, one per service index.
+fn supervise(
+    services_mutex: Arc<Mutex<Settings>>,
+    history_mutex: Arc<Mutex<History>>,
+    incidents_mutex: Arc<Mutex<Incidents>>,
+    wakeup: Arc<Condvar>,
+    listener: JoinHandle<Result<(), String>>,
+    handles: Vec<JoinHandle<()>>,
+) -> ! {
+    let mut listener = Some(listener);
+    let mut listener_started = Instant::now();
+    // How many times in a row the listener crashed soon after it was started
+    let mut listener_failures: u32 = 0;
+    // When the crashed listener should be restarted
+    let mut listener_restart: Option<Instant> = None;
+    let mut handles: Vec<Option<JoinHandle<()>>> = handles.into_iter().map(Some).collect();
+    // When each crashed test loop should be restarted
+    let mut restarts: Vec<Option<Instant>> = vec![None; handles.len()];
+
+    loop {
+        if let Some(restart_at) = listener_restart {
+            if Instant::now() >= restart_at {
+                listener_restart = None;
+                listener_started = Instant::now();
+                listener = Some(spawn_listener(
+                    Arc::clone(&services_mutex),
+                    Arc::clone(&incidents_mutex),
+                    Arc::clone(&wakeup),
+                    None,
+                ));
+            }
+        } else if listener.as_ref().is_some_and(|handle| handle.is_finished()) {
+            let reason = match listener.take().map(|handle| handle.join()) {
+                Some(Ok(Err(e))) => e,
+                joined => panic_reason(joined),
+            };
+            listener_failures = if listener_started.elapsed() >= LISTENER_MAX_RESTART_DELAY {
+                1
+            } else {
+                listener_failures + 1
+            };
+            let delay = LISTENER_RESTART_DELAY
+                .saturating_mul(2u32.saturating_pow(listener_failures - 1))
+                .min(LISTENER_MAX_RESTART_DELAY);
+            eprintln!("!  Listener crashed: {}, restarting in {}s", reason, delay.as_secs());
+            listener_restart = Some(Instant::now() + delay);
+        }
+
+        for index in 0..handles.len() {
+            if let Some(restart_at) = restarts[index] {
+                if Instant::now() >= restart_at {
+                    restarts[index] = None;
+                    handles[index] = Some(spawn_test_loop(
+                        Arc::clone(&services_mutex),
+                        Arc::clone(&history_mutex),
+                        Arc::clone(&incidents_mutex),
+                        Arc::clone(&wakeup),
+                        index,
+                    ));
+                }
+                continue;
+            }
+
+            if !handles[index].as_ref().is_some_and(|handle| handle.is_finished()) {
+                continue;
+            }
+            let reason = panic_reason(handles[index].take().map(|handle| handle.join()));
+
+            // The thread might have died while holding the lock
+            let mut locked_settings = services_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+            let service = &mut locked_settings.services[index];
+            eprintln!("!  Test loop of {} crashed: {}, restarting in {}s", service.name, reason, service.interval);
+            service.successes = 0.0;
+            service.status = Status::Unknown;
+            service.last_run = Some(Utc::now());
+            service.result = ResultOutput::Unknown(format!("Test loop crashed: {}", reason));
+            service.next_run = Some(Utc::now() + time::Duration::from_secs(service.interval));
+            restarts[index] = Some(Instant::now() + time::Duration::from_secs(service.interval));
+        }
+
+        // Done here rather than in the test loops, so they also run out while a test loop is crashed or running
+        let now = Utc::now();
+        for service in &mut services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services {
+            service.clear_expired(now);
+        }
+        thread::sleep(time::Duration::from_secs(1));
+    }
+}
+
+/// Why a thread finished, from the result of joining it
+fn panic_reason<T>(joined: Option<thread::Result<T>>) -> String {
+    match joined {
+        Some(Err(payload)) => payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string()),
+        _ => "exited".to_string(),
     }
 }
 
@@ -60,35 +362,125 @@ fn main() {
 /// # Arguments
 ///
 /// * `services_mutex` - An Arc Mutex that contains the settings.
+/// * `history_mutex` - An Arc Mutex that contains the run history.
+/// * `incidents_mutex` - An Arc Mutex that contains the incidents.
+/// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
 /// * `index` - The index of the service to be tested.
-fn test_loop(services_mutex: Arc<Mutex<Settings>>, index: usize) {
+fn test_loop(
+    services_mutex: Arc<Mutex<Settings>>,
+    history_mutex: Arc<Mutex<History>>,
+    incidents_mutex: Arc<Mutex<Incidents>>,
+    wakeup: Arc<Condvar>,
+    index: usize,
+) {
     loop {
-        let service = { services_mutex.lock().unwrap().services[index].clone() };
+        let service = { services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services[index].clone() };
         let interval = service.interval;
 
         // Pause checking if no internet
-        if service.pause_on_no_internet && !online::check(Some(12)).is_ok() {
+        if service.pause_on_no_internet && online::check(Some(12)).is_err() {
             println!("No internet, skipping {}", service.name);
-            let sleep_duration = match service.result {
+            let sleep_duration = match service.last_run {
                 // Means that it hasn't found any internet for as far as the program has ran
-                ResultOutput::Bool(state) if state == false => {
-                    time::Duration::from_millis((service.timeout * 1000.0) as u64)
-                }
+                None => time::Duration::from_millis((service.timeout * 1000.0) as u64),
                 // Means that it suddenly lost internet
-                _ => time::Duration::from_secs(interval / 5),
+                Some(_) => time::Duration::from_secs(interval / 5),
             };
-            thread::sleep(sleep_duration);
+            {
+                let mut locked_settings = services_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+                locked_settings.services[index].status = Status::Skipped;
+                locked_settings.services[index].next_run = Some(Utc::now() + sleep_duration);
+            }
+            wait_for_next_run(&services_mutex, &wakeup, index);
             continue;
         }
-        let (successes, test_result) = Tester::test(&service);
+        // Partial results are published while the test is still running
+        let publish = |partial: &ResultOutput| {
+            services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services[index].result = partial.clone();
+        };
+        services_mutex.lock().unwrap_or_else(PoisonError::into_inner).services[index].next_run = None;
+        let (status, (successes, test_result), raw_output) = Tester::test(&service, &publish);
 
         // Locking the resource, and updating it
-        {
-            let mut locked_settings = services_mutex.lock().unwrap();
+        let ran = {
+            let mut locked_settings = services_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+            let previous = locked_settings.services[index].status;
             locked_settings.services[index].successes = successes;
+            locked_settings.services[index].status = status;
+            locked_settings.services[index].clear_recovered(previous);
             locked_settings.services[index].last_run = Some(Utc::now());
             locked_settings.services[index].result = test_result;
+            locked_settings.services[index].stderr =
+                raw_output.as_ref().and_then(|raw| raw.stderr_snippet());
+            locked_settings.services[index].raw_output = raw_output.map(Arc::new);
+            locked_settings.services[index].next_run = Some(Utc::now() + time::Duration::from_secs(interval));
+            locked_settings.services[index].clone()
+        };
+        incidents_mutex.lock().unwrap_or_else(PoisonError::into_inner).update(&ran);
+        publish_run(&ran, &services_mutex, &history_mutex);
+        wait_for_next_run(&services_mutex, &wakeup, index);
+    }
+}
+
+/// Records the last run of a service in the history, and renders the status page and its badge with it.
+///
+/// The settings are only locked to copy what the page shows of the services, so they aren't blocked while the files are written.
+/// It's copied while holding the history, so pages rendered one after another never show an older state.
+///
+/// # Arguments
+///
+/// * `service` - The service right after it ran.
+/// * `services_mutex` - A Mutex that contains the settings.
+/// * `history_mutex` - A Mutex that contains the run history, also making sure only one page is rendered at a time.
+fn publish_run(service: &Service, services_mutex: &Mutex<Settings>, history_mutex: &Mutex<History>) {
+    let mut history = history_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+    history.record(service);
+    let (page, badges, services) = {
+        let locked_settings = services_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+        let services = match &locked_settings.status_page {
+            Some(_) => public_services(&locked_settings.services),
+            None => vec![],
+        };
+        (locked_settings.status_page.clone(), locked_settings.badges.clone(), services)
+    };
+    if let Some(page) = &page
+        && let Err(e) = status_page::render(page, &services, &history)
+    {
+        eprintln!("Unable to write the status page to {}: {}", page.directory, e);
+    }
+    if let Some(badges) = &badges
+        && let Some(public) = PublicService::from_service(service)
+        && let Err(e) = badges::write(badges, &public, &history)
+    {
+        eprintln!("Unable to write the badge of {} to {}: {}", service.name, badges.directory, e);
+    }
+}
+
+/// Waits until the next run of a service is due, or it was asked to run right away.
+/// Paused services wait until they are resumed, or asked to run.
+///
+/// # Arguments
+///
+/// * `services_mutex` - An Arc Mutex that contains the settings.
+/// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
+/// * `index` - The index of the service waiting.
+fn wait_for_next_run(services_mutex: &Mutex<Settings>, wakeup: &Condvar, index: usize) {
+    let mut locked_settings = services_mutex.lock().unwrap_or_else(PoisonError::into_inner);
+    loop {
+        let service = &mut locked_settings.services[index];
+        if service.run_requested {
+            service.run_requested = false;
+            return;
         }
-        thread::sleep(time::Duration::from_secs(interval));
+        let now = Utc::now();
+        let wait = match service.next_run {
+            _ if service.paused => None,
+            Some(next_run) if next_run > now => Some((next_run - now).to_std().unwrap_or_default()),
+            _ => return,
+        };
+        locked_settings = match wait {
+            Some(wait) => wakeup.wait_timeout(locked_settings, wait).unwrap_or_else(PoisonError::into_inner).0,
+            None => wakeup.wait(locked_settings).unwrap_or_else(PoisonError::into_inner),
+        };
     }
 }
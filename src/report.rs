@@ -0,0 +1,338 @@
+use chrono::prelude::*;
+use chrono::serde::ts_seconds;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::history::RunRecord;
+use crate::utils::partial_success::PartialSuccess;
+use crate::utils::status::Status;
+
+/// How many intervals the result of a run holds at most, a longer gap until the next run means the daemon wasn't running
+pub const MAX_SPAN_INTERVALS: i64 = 2;
+
+/// The availability of a single test of a service over the period of a report
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TestAvailability {
+    pub name: String,
+    pub runs: usize,
+    /// Between 0.0 and 1.0, `None` if the test didn't run in the period
+    pub availability: Option<f64>,
+}
+
+/// The uptime and outages of a service over a period
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Report {
+    pub service: String,
+    #[serde(with = "ts_seconds")]
+    pub from: DateTime<Utc>,
+    #[serde(with = "ts_seconds")]
+    pub to: DateTime<Utc>,
+    pub partial_success: PartialSuccess,
+    /// The runs in the period
+    pub runs: usize,
+    /// The seconds of the period known from the runs, i.e. not before the first run, while skipped or while the daemon was down
+    pub covered_seconds: i64,
+    /// Between 0.0 and 1.0, `None` if nothing is known about the period
+    pub uptime: Option<f64>,
+    /// The number of outages, a period of critical runs
+    pub outages: usize,
+    pub outage_seconds: i64,
+    pub longest_outage_seconds: i64,
+    /// Mean time to recovery, the average length of an outage
+    pub mttr_seconds: Option<i64>,
+    /// Mean time between failures, the covered time without outages divided by the number of outages
+    pub mtbf_seconds: Option<i64>,
+    pub tests: Vec<TestAvailability>,
+}
+
+/// Computes the uptime and outages of a service over a period.
+///
+/// The result of a run holds until the next run, so each run is weighted by how long it held. The run before the
+/// period, if any, tells the state at its start. Skipped and unknown runs leave their time out of the report, and so does
+/// the time a run would have held past `MAX_SPAN_INTERVALS` intervals, e.g. while the daemon was stopped.
+/// An outage starts with a critical run and lasts until the first ok or warning run.
+///
+/// # Arguments
+///
+/// * `service` - The name of the service.
+/// * `records` - The runs of the history, in the order they ran, up until `to`.
+/// * `interval` - The interval of the service in seconds.
+/// * `from` - The start of the period.
+/// * `to` - The end of the period, cut off at the current time.
+/// * `partial_success` - How runs that only partly succeeded count towards the uptime.
+pub fn report(
+    service: &str,
+    records: &[RunRecord],
+    interval: u64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    partial_success: PartialSuccess,
+) -> Report {
+    let to = to.min(Utc::now());
+    let max_span = Duration::seconds(interval as i64 * MAX_SPAN_INTERVALS);
+    let mut runs: Vec<&RunRecord> = records.iter().filter(|r| r.service == service && r.timestamp <= to).collect();
+    runs.sort_by_key(|r| r.timestamp);
+    let before = runs.iter().rposition(|r| r.timestamp < from);
+    let in_period = runs.iter().filter(|r| r.timestamp >= from).count();
+
+    // Every run from the one before the period on, along with the part of the period it held
+    let start = before.unwrap_or(runs.len() - in_period);
+    let mut spans: Vec<(&RunRecord, i64)> = vec![];
+    for (i, run) in runs.iter().enumerate().skip(start) {
+        let begins = run.timestamp.max(from);
+        let ends = runs.get(i + 1).map(|next| next.timestamp).unwrap_or(to).min(run.timestamp + max_span);
+        spans.push((run, (ends - begins).num_seconds().max(0)));
+    }
+
+    let mut covered_seconds = 0;
+    let mut available_seconds = 0.0;
+    let mut outages = 0;
+    let mut outage_seconds = 0;
+    let mut longest_outage_seconds = 0;
+    let mut current_outage: Option<i64> = None;
+    let mut tests: BTreeMap<String, (usize, i64, f64)> = BTreeMap::new();
+    for (run, seconds) in &spans {
+        if !run.counts() {
+            continue;
+        }
+        for test in &run.tests {
+            let (test_runs, test_seconds, test_available) = tests.entry(test.name.clone()).or_default();
+            if run.timestamp >= from {
+                *test_runs += 1;
+            }
+            *test_seconds += seconds;
+            *test_available += partial_success.availability(test.success) * *seconds as f64;
+        }
+        // E.g. the run before the period, when the next run started right at its start
+        if *seconds == 0 {
+            continue;
+        }
+        covered_seconds += seconds;
+        available_seconds += partial_success.availability(run.successes) * *seconds as f64;
+
+        current_outage = match (run.status == Status::Critical, current_outage) {
+            (true, Some(outage)) => Some(outage + seconds),
+            (true, None) => {
+                outages += 1;
+                Some(*seconds)
+            }
+            (false, _) => None,
+        };
+        if let Some(outage) = current_outage {
+            outage_seconds += seconds;
+            longest_outage_seconds = longest_outage_seconds.max(outage);
+        }
+    }
+
+    let ratio = |available: f64, seconds: i64| if seconds > 0 { Some(available / seconds as f64) } else { None };
+    Report {
+        service: service.to_string(),
+        from,
+        to,
+        partial_success,
+        runs: in_period,
+        covered_seconds,
+        uptime: ratio(available_seconds, covered_seconds),
+        outages,
+        outage_seconds,
+        longest_outage_seconds,
+        mttr_seconds: (outages > 0).then(|| outage_seconds / outages as i64),
+        mtbf_seconds: (outages > 0).then(|| (covered_seconds - outage_seconds) / outages as i64),
+        tests: tests
+            .into_iter()
+            .map(|(name, (runs, seconds, available))| TestAvailability {
+                name,
+                runs,
+                availability: ratio(available, seconds),
+            })
+            .collect(),
+    }
+}
+
+/// Formats reports as CSV, a line for every service followed by a line for each of its tests
+pub fn to_csv(reports: &[Report]) -> String {
+    let mut csv = String::from(
+        "service,test,from,to,partial_success,runs,covered_seconds,availability,outages,outage_seconds,longest_outage_seconds,mttr_seconds,mtbf_seconds\n",
+    );
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    for report in reports {
+        let time = |time: &DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let (from, to) = (time(&report.from), time(&report.to));
+        csv.push_str(&format!(
+            "{},,{},{},{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&report.service),
+            from,
+            to,
+            report.partial_success,
+            report.runs,
+            report.covered_seconds,
+            optional(report.uptime.map(|u| format!("{:.6}", u))),
+            report.outages,
+            report.outage_seconds,
+            report.longest_outage_seconds,
+            optional(report.mttr_seconds.map(|s| s.to_string())),
+            optional(report.mtbf_seconds.map(|s| s.to_string())),
+        ));
+        for test in &report.tests {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},,{},,,,,\n",
+                csv_field(&report.service),
+                csv_field(&test.name),
+                from,
+                to,
+                report.partial_success,
+                test.runs,
+                optional(test.availability.map(|a| format!("{:.6}", a))),
+            ));
+        }
+    }
+    csv
+}
+
+/// Quotes a CSV field if it contains anything that would break the line
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::TestRecord;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_714_521_600 + seconds, 0).unwrap()
+    }
+
+    fn run(seconds: i64, status: Status, successes: f64) -> RunRecord {
+        RunRecord {
+            timestamp: at(seconds),
+            service: "web".to_string(),
+            status,
+            successes,
+            tests: vec![],
+        }
+    }
+
+    fn report_with(runs: &[RunRecord], partial_success: PartialSuccess) -> Report {
+        report("web", runs, 60, at(0), at(240), partial_success)
+    }
+
+    #[test]
+    fn weights_the_uptime_by_how_long_each_run_held() {
+        let runs = [
+            run(0, Status::Ok, 1.0),
+            run(60, Status::Warning, 0.5),
+            run(120, Status::Critical, 0.0),
+            run(180, Status::Ok, 1.0),
+        ];
+        let result = report_with(&runs, PartialSuccess::Weighted);
+        assert_eq!(result.runs, 4);
+        assert_eq!(result.covered_seconds, 240);
+        assert_eq!(result.uptime, Some(2.5 / 4.0));
+
+        let result = report_with(&runs, PartialSuccess::Up);
+        assert_eq!(result.uptime, Some(0.75));
+        let result = report_with(&runs, PartialSuccess::Down);
+        assert_eq!(result.uptime, Some(0.5));
+    }
+
+    #[test]
+    fn takes_the_state_at_the_start_from_the_run_before_the_period() {
+        let runs = [run(-30, Status::Critical, 0.0), run(30, Status::Ok, 1.0)];
+        let result = report("web", &runs, 60, at(0), at(90), PartialSuccess::Weighted);
+        assert_eq!(result.runs, 1);
+        assert_eq!(result.covered_seconds, 90);
+        assert_eq!(result.uptime, Some(60.0 / 90.0));
+        assert_eq!(result.outages, 1);
+        assert_eq!(result.outage_seconds, 30);
+    }
+
+    #[test]
+    fn leaves_out_gaps_longer_than_the_interval_allows() {
+        // The daemon was down between the runs at 60 and 1000
+        let runs = [run(0, Status::Ok, 1.0), run(60, Status::Critical, 0.0), run(1000, Status::Ok, 1.0)];
+        let result = report("web", &runs, 60, at(0), at(1060), PartialSuccess::Weighted);
+        assert_eq!(result.covered_seconds, 60 + 120 + 60);
+        assert_eq!(result.uptime, Some(120.0 / 240.0));
+        assert_eq!(result.outage_seconds, 120);
+
+        // Nor is the time after the last run counted once the daemon stopped
+        let result = report("web", &runs[..1], 60, at(0), at(1000), PartialSuccess::Weighted);
+        assert_eq!(result.covered_seconds, 120);
+        assert_eq!(result.uptime, Some(1.0));
+    }
+
+    #[test]
+    fn leaves_out_skipped_and_unknown_runs() {
+        let runs = [run(0, Status::Ok, 1.0), run(60, Status::Skipped, 0.0), run(120, Status::Critical, 0.0)];
+        let result = report("web", &runs, 60, at(0), at(180), PartialSuccess::Weighted);
+        assert_eq!(result.covered_seconds, 120);
+        assert_eq!(result.uptime, Some(0.5));
+
+        // E.g. output that couldn't be parsed in the middle of an outage, which doesn't end it
+        let runs = [
+            run(0, Status::Critical, 0.0),
+            run(60, Status::Unknown, 0.0),
+            run(120, Status::Critical, 0.0),
+            run(180, Status::Ok, 1.0),
+        ];
+        let result = report("web", &runs, 60, at(0), at(240), PartialSuccess::Weighted);
+        assert_eq!(result.covered_seconds, 180);
+        assert_eq!(result.uptime, Some(60.0 / 180.0));
+        assert_eq!(result.outages, 1);
+        assert_eq!(result.outage_seconds, 120);
+    }
+
+    #[test]
+    fn computes_outages_mttr_and_mtbf() {
+        let runs = [
+            run(0, Status::Ok, 1.0),
+            run(60, Status::Critical, 0.0),
+            run(120, Status::Critical, 0.0),
+            run(180, Status::Warning, 0.5),
+            run(240, Status::Ok, 1.0),
+            run(300, Status::Critical, 0.0),
+            run(360, Status::Ok, 1.0),
+        ];
+        let result = report("web", &runs, 60, at(0), at(420), PartialSuccess::Weighted);
+        assert_eq!(result.outages, 2);
+        assert_eq!(result.outage_seconds, 180);
+        assert_eq!(result.longest_outage_seconds, 120);
+        assert_eq!(result.mttr_seconds, Some(90));
+        assert_eq!(result.mtbf_seconds, Some((420 - 180) / 2));
+    }
+
+    #[test]
+    fn reports_nothing_without_runs() {
+        let runs = [run(0, Status::Ok, 1.0)];
+        let result = report("other", &runs, 60, at(0), at(60), PartialSuccess::Weighted);
+        assert_eq!(result.runs, 0);
+        assert_eq!(result.covered_seconds, 0);
+        assert_eq!(result.uptime, None);
+        assert_eq!(result.mttr_seconds, None);
+        assert_eq!(result.mtbf_seconds, None);
+    }
+
+    #[test]
+    fn computes_the_availability_of_each_test() {
+        let test = |name: &str, success: f64| TestRecord {
+            name: name.to_string(),
+            success,
+            status: None,
+        };
+        let mut first = run(0, Status::Warning, 0.5);
+        first.tests = vec![test("http", 1.0), test("tls", 0.0)];
+        let mut second = run(60, Status::Ok, 1.0);
+        second.tests = vec![test("http", 1.0), test("tls", 1.0)];
+        let result = report("web", &[first, second], 60, at(0), at(120), PartialSuccess::Weighted);
+        assert_eq!(result.tests.len(), 2);
+        assert_eq!((result.tests[0].name.as_str(), result.tests[0].runs), ("http", 2));
+        assert_eq!(result.tests[0].availability, Some(1.0));
+        assert_eq!(result.tests[1].availability, Some(0.5));
+    }
+}
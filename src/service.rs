@@ -1,27 +1,74 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 use chrono::prelude::*;
 use chrono::serde::ts_seconds_option;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::settings::{ResultOutput, Settings};
+use crate::acknowledgement::{Acknowledgement, Silence};
+use crate::settings::{RawOutput, ResultOutput, Settings};
+use crate::status_page::PublicSettings;
+use crate::utils::exit_codes::{nagios_exit_codes, parse_exit_codes, ExitCodeValue};
+use crate::utils::interpolation::interpolate;
+use crate::utils::output_format::OutputFormat;
+use crate::utils::partial_success::PartialSuccess;
 use crate::utils::retry_strategy::RetryStrategy;
+use crate::utils::status::{Status, StatusThresholds};
 
 /// The `Service` struct represents a service that can be tested.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Service {
     pub name: String,
+    pub tags: Vec<String>,
+    pub group: Option<String>,
     pub command: String,
     pub args: Option<Vec<String>>,
+    /// Extra environment variables for the command
+    pub env: HashMap<String, String>,
     pub interval: u64,
     pub timeout: f64,
     #[serde(with = "ts_seconds_option")]
     pub last_run: Option<DateTime<Utc>>,
+    /// When the service runs next, `None` while it's running
+    #[serde(with = "ts_seconds_option")]
+    pub next_run: Option<DateTime<Utc>>,
+    /// Paused services only run when explicitly asked to
+    pub paused: bool,
+    /// Set by the `ack` command while the failure is being worked on
+    pub acknowledged: Option<Acknowledgement>,
+    /// Set by the `silence` command to keep the service out of `service --unhandled` for a while
+    pub silenced: Option<Silence>,
     pub successes: f64,
+    pub status: Status,
     pub pause_on_no_internet: bool,
     pub retry_counter: i64,
     pub retry_strategy: RetryStrategy,
+    pub max_output_bytes: usize,
+    pub stderr_is_warning: bool,
+    pub format: OutputFormat,
+    /// A report file to read the output from after the command exits, instead of stdout
+    pub report: Option<String>,
+    pub exit_codes: HashMap<i32, ExitCodeValue>,
+    pub thresholds: StatusThresholds,
+    /// How runs that only partly succeeded count towards the uptime in reports
+    pub partial_success: PartialSuccess,
     pub result: ResultOutput,
+    /// How the service is shown on the public status page
+    pub public: PublicSettings,
+    /// The settings file the service was defined in
+    pub source: String,
+    /// Whatever a successful run wrote to stderr (truncated)
+    pub stderr: Option<String>,
+    /// The raw output of the last run, only shown when explicitly asked for. Shared, so copies of the service stay cheap
+    #[serde(skip)]
+    pub raw_output: Option<Arc<RawOutput>>,
+    /// Set by the `run` command to run the service without waiting for its interval
+    #[serde(skip)]
+    pub run_requested: bool,
+    /// The interpolated command, args and env values mapped to how they were written in the settings
+    #[serde(skip)]
+    placeholders: HashMap<String, String>,
 }
 impl Service {
     /// Creates a new `Service` instance.
@@ -30,28 +77,38 @@ impl Service {
     ///
     /// * `value` - A reference to a `Value` that contains the service settings.
     /// * `settings` - A `Settings` instance that contains the global settings in case it isn't defined in the service settings.
+    /// * `source` - The settings file the service is defined in.
     ///
     /// # Returns
     ///
     /// A new `Service` instance.
-    pub fn new(value: &Value, settings: Settings) -> Self {
-        let name = value
-            .get("name")
-            .expect("Missing name value in service")
-            .as_str()
-            .expect("Name is not a valid string");
-        let command = value
-            .get("command")
-            .expect("Missing command value in service")
-            .as_str()
-            .expect("Command is not a valid string");
-        let args: Option<Vec<String>> = value.get("args").map(|v| {
-            v.as_array()
-                .expect("args is not a valid array")
-                .iter()
-                .map(|s| s.as_str().expect("arg is not a valid string").to_string())
+    pub fn new(value: &Value, settings: Settings, source: &str) -> Self {
+        // The settings are validated before services are created, so missing values only end up empty here
+        let name = value.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        // Interpolated values may contain secrets, so remember how they were written in order to show that instead
+        let mut placeholders: HashMap<String, String> = HashMap::new();
+        let mut resolve = |value: &str| {
+            let interpolated = interpolate(value).unwrap_or_default();
+            if interpolated != value {
+                placeholders.insert(interpolated.clone(), value.to_string());
+            }
+            interpolated
+        };
+        let command = resolve(value.get("command").and_then(|v| v.as_str()).unwrap_or_default());
+        let args: Option<Vec<String>> = value.get("args").and_then(|v| v.as_array()).map(|arr| {
+            arr.iter()
+                .filter_map(|s| s.as_str().map(&mut resolve))
                 .collect()
         });
+        let env: HashMap<String, String> = value
+            .get("env")
+            .and_then(|v| v.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(k, v)| Some((k.clone(), resolve(&env_value(v)?))))
+                    .collect()
+            })
+            .unwrap_or_default();
         let interval = value
             .get("interval")
             .and_then(|v| v.as_u64())
@@ -71,31 +128,177 @@ impl Service {
         let retry_strategy = value
             .get("retry_strategy")
             .and_then(|v| v.as_str())
-            .and_then(|s| RetryStrategy::from_str(s))
+            .and_then(RetryStrategy::from_str)
             .unwrap_or(settings.retry_strategy);
+        let max_output_bytes = value
+            .get("max_output_bytes")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(settings.max_output_bytes);
+        let stderr_is_warning = value
+            .get("stderr_is_warning")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(settings.stderr_is_warning);
+        let format = value
+            .get("format")
+            .and_then(|v| v.as_str())
+            .and_then(OutputFormat::from_str)
+            .unwrap_or(OutputFormat::Auto);
+        let report = value
+            .get("report")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let tags: Vec<String> = value
+            .get("tags")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        let group = value
+            .get("group")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let exit_codes = value
+            .get("exit_codes")
+            .map(parse_exit_codes)
+            .unwrap_or_default();
+        let thresholds = value
+            .get("thresholds")
+            .map(|v| StatusThresholds::from_value(v, &settings.thresholds))
+            .unwrap_or(settings.thresholds);
+        let partial_success = value
+            .get("partial_success")
+            .and_then(|v| v.as_str())
+            .and_then(PartialSuccess::from_str)
+            .unwrap_or(settings.partial_success);
 
         Service {
             name: String::from(name),
-            command: String::from(command),
+            tags,
+            group,
+            command,
             args,
+            env,
             interval,
             timeout,
             last_run: None,
+            next_run: None,
+            paused: false,
+            acknowledged: None,
+            silenced: None,
             pause_on_no_internet,
             successes: 0.00,
+            status: Status::Pending,
             retry_counter,
             retry_strategy,
+            max_output_bytes,
+            stderr_is_warning,
+            format,
+            report,
+            exit_codes,
+            thresholds,
+            partial_success,
             result: ResultOutput::Bool(false),
+            public: PublicSettings::from_value(value.get("public")),
+            source: source.to_string(),
+            stderr: None,
+            raw_output: None,
+            run_requested: false,
+            placeholders,
+        }
+    }
+
+    /// Returns a copy of the service safe to show to clients, with the interpolated command, args and env values
+    /// shown as they were written in the settings, e.g. `${API_TOKEN}`.
+    pub fn redacted(&self) -> Service {
+        let redact = |value: &String| self.placeholders.get(value).unwrap_or(value).clone();
+        Service {
+            command: redact(&self.command),
+            args: self.args.as_ref().map(|args| args.iter().map(redact).collect()),
+            env: self.env.iter().map(|(k, v)| (k.clone(), redact(v))).collect(),
+            ..self.clone()
         }
     }
+
+    /// Whether someone already knows about the service, because it's acknowledged or silenced
+    pub fn is_handled(&self) -> bool {
+        self.acknowledged.is_some() || self.silenced.is_some()
+    }
+
+    /// Clears the acknowledgement if the last run recovered from the problem it was acknowledged for, meaning it's
+    /// `ok` or `warning` (see `Status::is_recovered`) and wasn't before. An acknowledged warning stays acknowledged
+    /// while it lasts, and a failure is recovered from by the same run that resolves its incident.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The status before the last run.
+    pub fn clear_recovered(&mut self, previous: Status) {
+        if self.status.is_recovered() && self.status != previous {
+            self.acknowledged = None;
+        }
+    }
+
+    /// Clears the acknowledgement and the silence once they run out.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time.
+    pub fn clear_expired(&mut self, now: DateTime<Utc>) {
+        if self
+            .acknowledged
+            .as_ref()
+            .is_some_and(|ack| ack.until.is_some_and(|until| until <= now))
+        {
+            self.acknowledged = None;
+        }
+        if self.silenced.as_ref().is_some_and(|silence| silence.until <= now) {
+            self.silenced = None;
+        }
+    }
+
+    /// Looks up what an exit code means for this service.
+    ///
+    /// The `exit_codes` mapping of the service takes priority. When using the `nagios` format,
+    /// the Nagios semantics (0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN) are used as a fallback.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The exit code of the command, `None` if it was terminated by a signal.
+    pub fn exit_code_value(&self, code: Option<i32>) -> Option<ExitCodeValue> {
+        let code = code?;
+        if let Some(value) = self.exit_codes.get(&code) {
+            return Some(value.clone());
+        }
+        if self.format == OutputFormat::Nagios {
+            return nagios_exit_codes().remove(&code);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+impl Service {
+    /// A service from its settings alone, with the default settings for everything it leaves out
+    pub(crate) fn for_tests(value: &Value) -> Service {
+        Service::new(value, crate::settings::default_settings(), "settings.json")
+    }
+}
+
+/// Converts a value of the `env` object to a string, numbers and booleans are accepted as well.
+pub fn env_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
 }
 
 impl fmt::Display for Service {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "name: {}, command: {}, interval: {}, timeout: {}, retry counter: {}",
-            self.name, self.command, self.interval, self.timeout, self.retry_counter
+            "name: {}, command: {}, interval: {}, timeout: {}, retry counter: {}, source: {}",
+            self.name, self.command, self.interval, self.timeout, self.retry_counter, self.source
         )
     }
 }
\ No newline at end of file
@@ -1,21 +1,49 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::path::Path;
 use std::{fmt, fs};
+use crate::badges::BadgeSettings;
+use crate::history::HistorySettings;
 use crate::service::Service;
-use crate::utils::jsonc::strip_jsonc_comments;
+use crate::status_page::StatusPageSettings;
+use crate::utils::partial_success::PartialSuccess;
 use crate::utils::protocol::Protocol;
 use crate::utils::retry_strategy::RetryStrategy;
+use crate::utils::ring_buffer::RingBuffer;
+use crate::utils::settings_format::{ParsedSettings, SettingsFormat};
+use crate::utils::status::{Status, StatusThresholds};
+use crate::utils::templates::{expand_services, merge_templates};
+use crate::validation::{has_errors, Problem, Severity, Validation};
+use crate::zmq_handler::access::AccessSettings;
+use crate::zmq_handler::curve::CurveSettings;
 
-fn default_settings() -> Settings {
+pub(crate) fn default_settings() -> Settings {
     Settings {
         protocol: Protocol::Tcp,
         port: 5747,
+        bind: "*".to_string(),
+        endpoints: vec![],
+        ipc_permissions: None,
         interval: 600,
         timeout: 60.0,
         pause_on_no_internet: false,
         services: vec![],
         retry_counter: 0,
         retry_strategy: RetryStrategy::Worst,
+        max_output_bytes: 1024 * 1024,
+        stderr_is_warning: false,
+        thresholds: StatusThresholds {
+            ok: 1.0,
+            warning: 0.5,
+        },
+        partial_success: PartialSuccess::Weighted,
+        curve: None,
+        access: None,
+        audit_log: None,
+        history: None,
+        status_page: None,
+        badges: None,
+        incidents_file: None,
     }
 }
 
@@ -24,43 +52,147 @@ pub struct TestResult {
     pub(crate) name: String,
     pub(crate) success: f64,
     pub(crate) result: Value,
+    /// Left empty by the parsers unless they know better, filled in from the thresholds afterwards
+    #[serde(default)]
+    pub(crate) status: Option<Status>,
 }
 
+/// The raw captured output of the last run of a service, useful for debugging parsers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+}
+impl RawOutput {
+    /// The maximum amount of stderr bytes kept on the service result
+    const STDERR_SNIPPET_BYTES: usize = 2048;
+
+    /// Returns the (truncated) stderr of a successful run, or `None` if it failed or nothing was written to stderr.
+    pub fn stderr_snippet(&self) -> Option<String> {
+        if self.exit_code != Some(0) || self.stderr.trim().is_empty() {
+            return None;
+        }
+        let mut buffer = RingBuffer::new(Self::STDERR_SNIPPET_BYTES);
+        buffer.push(self.stderr.as_bytes());
+        Some(buffer.into_string())
+    }
+}
+
+/// The result of a run, shown as is except for `Unknown`, which is tagged to tell it apart from a `String`
 #[derive(Deserialize, Serialize, Clone, Debug)]
-#[serde(untagged)]
 pub enum ResultOutput {
+    /// The outcome of the test couldn't be determined, with a description why, e.g. `{"unknown": "Invalid JSON output"}`
+    #[serde(rename = "unknown")]
+    Unknown(String),
+    #[serde(untagged)]
     String(String),
+    #[serde(untagged)]
     Bool(bool),
+    #[serde(untagged)]
     Int(i32),
+    #[serde(untagged)]
     Float(f32),
+    #[serde(untagged)]
     Null,
+    #[serde(untagged)]
     Result(Vec<TestResult>),
 }
 impl ResultOutput {
     pub fn to_successes(&self) -> f64 {
         match self {
-            ResultOutput::Null => 1.0, // Why success on null?
+            ResultOutput::Null => 1.0, // No output, but the exit code was 0
             ResultOutput::String(_) => 1.0,
             ResultOutput::Bool(b) => *b as i32 as f64,
             ResultOutput::Int(i) => *i as f64,
             ResultOutput::Float(f) => *f as f64,
+            ResultOutput::Result(v) if v.is_empty() => 0.0,
             ResultOutput::Result(v) => {
                 v.iter().map(|val| val.success).sum::<f64>() / v.len() as f64
             }
+            ResultOutput::Unknown(_) => 0.0,
+        }
+    }
+
+    /// Computes the `Status` of a test run, also filling in the status of every `TestResult`.
+    /// A run in which every test was skipped is skipped as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `successes` - The success score of the run.
+    /// * `thresholds` - The thresholds mapping success scores onto a status.
+    pub fn evaluate_status(&mut self, successes: f64, thresholds: &StatusThresholds) -> Status {
+        match self {
+            ResultOutput::Unknown(_) => Status::Unknown,
+            ResultOutput::Result(v) => {
+                for test_result in v.iter_mut() {
+                    if test_result.status.is_none() {
+                        test_result.status = Some(thresholds.status_of(test_result.success));
+                    }
+                }
+                if !v.is_empty() && v.iter().all(|t| t.status == Some(Status::Skipped)) {
+                    return Status::Skipped;
+                }
+                thresholds.status_of(successes)
+            }
+            _ => thresholds.status_of(successes),
         }
     }
 }
 
+/// Parses file permissions written in octal, e.g. `"0660"` or `"660"`
+pub fn parse_permissions(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+}
+
+/// The combined state of the services sharing a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupSummary {
+    pub group: String,
+    /// The average success score of the services that ran, `None` if none of them did yet
+    pub successes: Option<f64>,
+    pub status: Status,
+    pub services: Vec<String>,
+    /// The services of the group with a warning, critical or unknown status
+    pub problems: Vec<String>,
+}
+
 /// Global settings
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Settings {
     pub protocol: Protocol,
     pub port: u16,
+    /// The address `protocol` and `port` are bound on, `*` for all interfaces
+    pub bind: String,
+    /// Full ZMQ endpoints to bind on instead of `protocol`, `bind` and `port`, e.g. `ipc:///run/status.sock`
+    pub endpoints: Vec<String>,
+    /// The file permissions of IPC sockets, e.g. `0o660`
+    pub ipc_permissions: Option<u32>,
     pub interval: u64,
     pub timeout: f64,
     pub pause_on_no_internet: bool,
     pub retry_counter: i64,
     pub retry_strategy: RetryStrategy,
+    pub max_output_bytes: usize,
+    pub stderr_is_warning: bool,
+    pub thresholds: StatusThresholds,
+    pub partial_success: PartialSuccess,
+    /// CURVE authentication and encryption of the control socket, disabled if `None`
+    pub curve: Option<CurveSettings>,
+    /// Which clients may use which commands, everyone may use everything if `None`
+    pub access: Option<AccessSettings>,
+    /// The file every request to the control socket is appended to, no audit log if `None`
+    pub audit_log: Option<String>,
+    /// Where the results of every run are kept, no history if `None`
+    pub history: Option<HistorySettings>,
+    /// Where the public status page is written to after every run, no status page if `None`
+    pub status_page: Option<StatusPageSettings>,
+    /// Where the SVG badges of the services are written to after every run, no badges if `None`
+    pub badges: Option<BadgeSettings>,
+    /// The file the incidents are kept in, only kept in memory if `None`
+    pub incidents_file: Option<String>,
     pub services: Vec<Service>,
 }
 
@@ -70,39 +202,102 @@ impl Settings {
     /// # Arguments
     ///
     /// * `json` - A `Value` that contains the settings.
-    fn bare(json: Value) -> Self {
-        let default_settings = default_settings();
+    /// * `default_settings` - The settings used for anything not defined in `json`.
+    fn bare(json: &Value, default_settings: &Settings) -> Self {
 
         let protocol = json
             .get("protocol")
             .and_then(|v| v.as_str())
-            .and_then(|s| Protocol::from_str(s))
-            .unwrap_or(default_settings.protocol);
+            .and_then(Protocol::from_str)
+            .unwrap_or(default_settings.protocol.clone());
         let port = json
             .get("port")
             .and_then(|v| v.as_u64())
-            .unwrap_or_else(|| default_settings.port as u64) as u16;
+            .unwrap_or(default_settings.port as u64) as u16;
+        let bind = json
+            .get("bind")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or(default_settings.bind.clone());
+        let endpoints = json
+            .get("endpoints")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|e| e.as_str().map(String::from)).collect())
+            .unwrap_or(default_settings.endpoints.clone());
+        let ipc_permissions = json
+            .get("ipc_permissions")
+            .and_then(|v| v.as_str())
+            .and_then(parse_permissions)
+            .or(default_settings.ipc_permissions);
         let interval = json
             .get("interval")
             .and_then(|v| v.as_u64())
-            .unwrap_or_else(|| default_settings.interval);
+            .unwrap_or(default_settings.interval);
         let timeout = json
             .get("timeout")
             .and_then(|v| v.as_f64())
-            .unwrap_or_else(|| default_settings.timeout);
+            .unwrap_or(default_settings.timeout);
         let pause_on_no_internet = json
             .get("pause_on_no_internet")
             .and_then(|v| v.as_bool())
-            .unwrap_or_else(|| default_settings.pause_on_no_internet);
+            .unwrap_or(default_settings.pause_on_no_internet);
         let retry_counter = json
             .get("retry_counter")
             .and_then(|v| v.as_i64())
-            .unwrap_or_else(|| default_settings.retry_counter);
+            .unwrap_or(default_settings.retry_counter);
         let retry_strategy = json
             .get("retry_strategy")
             .and_then(|v| v.as_str())
-            .and_then(|s| RetryStrategy::from_str(s))
-            .unwrap_or_else(|| default_settings.retry_strategy);
+            .and_then(RetryStrategy::from_str)
+            .unwrap_or(default_settings.retry_strategy.clone());
+        let max_output_bytes = json
+            .get("max_output_bytes")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(default_settings.max_output_bytes);
+        let stderr_is_warning = json
+            .get("stderr_is_warning")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default_settings.stderr_is_warning);
+        let thresholds = json
+            .get("thresholds")
+            .map(|v| StatusThresholds::from_value(v, &default_settings.thresholds))
+            .unwrap_or(default_settings.thresholds.clone());
+        let partial_success = json
+            .get("partial_success")
+            .and_then(|v| v.as_str())
+            .and_then(PartialSuccess::from_str)
+            .unwrap_or(default_settings.partial_success);
+        let curve = json
+            .get("curve")
+            .and_then(CurveSettings::from_value)
+            .or(default_settings.curve.clone());
+        let access = json
+            .get("access")
+            .and_then(AccessSettings::from_value)
+            .or(default_settings.access.clone());
+        let audit_log = json
+            .get("audit_log")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or(default_settings.audit_log.clone());
+        let history = json
+            .get("history")
+            .and_then(HistorySettings::from_value)
+            .or(default_settings.history.clone());
+        let status_page = json
+            .get("status_page")
+            .and_then(StatusPageSettings::from_value)
+            .or(default_settings.status_page.clone());
+        let badges = json
+            .get("badges")
+            .and_then(BadgeSettings::from_value)
+            .or(default_settings.badges.clone());
+        let incidents_file = json
+            .get("incidents_file")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or(default_settings.incidents_file.clone());
         let services: Vec<Service> = vec![];
 
         // Do NOT create the service here!
@@ -111,11 +306,25 @@ impl Settings {
         Settings {
             protocol,
             port,
+            bind,
+            endpoints,
+            ipc_permissions,
             interval,
             timeout,
             pause_on_no_internet,
             retry_counter,
             retry_strategy,
+            max_output_bytes,
+            stderr_is_warning,
+            thresholds,
+            partial_success,
+            curve,
+            access,
+            audit_log,
+            history,
+            status_page,
+            badges,
+            incidents_file,
             services,
         }
     }
@@ -124,42 +333,212 @@ impl Settings {
     ///
     /// # Arguments
     ///
-    /// * `path` - An optional string that represents the path to the JSON file. If no path is provided, "settings.json" is used by default.
-    pub fn new(path: Option<String>) -> Self {
+    /// * `path` - An optional string that represents the path to the settings file. If no path is provided, "settings.json" is used by default.
+    ///   The format is detected by the file extension, `.yaml`/`.yml` for YAML, `.toml` for TOML and anything else for JSON(C).
+    ///
+    /// # Returns
+    ///
+    /// The settings along with any warnings found while validating them, or every problem found if the settings are invalid.
+    pub fn new(path: Option<String>) -> Result<(Self, Vec<Problem>), Vec<Problem>> {
         let path: String = path.unwrap_or("settings.json".to_string());
 
         println!("Settings path:\t{}", path);
 
-        let file_content = match fs::read_to_string(path) {
-            Ok(fc) => fc,
-            Err(error) => panic!("Unable to open the file: {}", error),
-        };
-        // In case the person is using JSONC, just run it through this comment-stripper
-        let stripped_json = strip_jsonc_comments(&file_content, true);
-        let json: Value = serde_json::from_str(&stripped_json).expect("file should be proper JSON");
+        let main = Settings::load_file(&path)?;
+        let mut validation = Validation::default();
+        let main_templates = merge_templates(&main.json, &Map::new());
+        validation.file(&path, main.locations.clone(), &main.json, false, &main_templates);
+
+        // Included files are looked up relative to the main settings file
+        let directory = Path::new(&path).parent().unwrap_or(Path::new(""));
+        let mut included: Vec<(String, Value)> = vec![];
+        let patterns = main.json.get("include").and_then(|v| v.as_array());
+        for (i, pattern) in patterns.into_iter().flatten().enumerate() {
+            let Some(pattern) = pattern.as_str() else {
+                continue;
+            };
+            let include_path = format!("include[{}]", i);
+            let location = main.locations.get(&include_path).copied();
+            let problem = |message: String| Problem::error(&path, &include_path, location, message);
+            let full_pattern = directory.join(pattern).to_string_lossy().to_string();
+            let paths = match glob::glob(&full_pattern) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    validation.push(problem(format!("Invalid glob pattern \"{}\": {}", pattern, e)));
+                    continue;
+                }
+            };
+            let mut matched = false;
+            for entry in paths {
+                matched = true;
+                let file = match entry {
+                    Ok(file) => file.to_string_lossy().to_string(),
+                    Err(e) => {
+                        validation.push(problem(format!("Unable to read {}", e)));
+                        continue;
+                    }
+                };
+                if file == path || included.iter().any(|(f, _)| *f == file) {
+                    continue;
+                }
+                match Settings::load_file(&file) {
+                    Ok(parsed) => {
+                        let templates = merge_templates(&parsed.json, &main_templates);
+                        validation.file(&file, parsed.locations, &parsed.json, true, &templates);
+                        included.push((file, parsed.json));
+                    }
+                    Err(problems) => problems.into_iter().for_each(|p| validation.push(p)),
+                }
+            }
+            if !matched {
+                validation.push(Problem {
+                    severity: Severity::Warning,
+                    ..problem(format!("\"{}\" didn't match any files", pattern))
+                });
+            }
+        }
+
+        let problems = validation.problems;
+        if has_errors(&problems) {
+            return Err(problems);
+        }
 
         // Here we create the bare-bone settings. Needed in order to reference parent JSON in services
-        let settings = Settings::bare(json.clone());
+        let settings = Settings::bare(&main.json, &default_settings());
 
-        let services_try = json.get("services").and_then(|v| v.as_array());
-        let services = match services_try {
-            None => default_settings().services,
-            Some(arr) => arr
-                .iter()
-                .map(|s| Service::new(s, settings.clone()))
-                .collect(),
-        };
+        let mut services: Vec<Service> = vec![];
+        let sources = std::iter::once((path.clone(), main.json)).chain(included);
+        for (file, json) in sources {
+            // An included file can have its own defaults for its services, falling back to the main settings
+            let file_settings = Settings::bare(&json, &settings);
+            let (expanded, _) = expand_services(&json, &merge_templates(&json, &main_templates));
+            services.extend(expanded.iter().map(|s| Service::new(&s.value, file_settings.clone(), &file)));
+        }
 
-        Settings {
+        let settings = Settings {
             protocol: settings.protocol,
             port: settings.port,
+            bind: settings.bind,
+            endpoints: settings.endpoints,
+            ipc_permissions: settings.ipc_permissions,
             interval: settings.interval,
             timeout: settings.timeout,
             pause_on_no_internet: settings.pause_on_no_internet,
             retry_counter: settings.retry_counter,
             retry_strategy: settings.retry_strategy,
+            max_output_bytes: settings.max_output_bytes,
+            stderr_is_warning: settings.stderr_is_warning,
+            thresholds: settings.thresholds,
+            partial_success: settings.partial_success,
+            curve: settings.curve,
+            access: settings.access,
+            audit_log: settings.audit_log,
+            history: settings.history,
+            status_page: settings.status_page,
+            badges: settings.badges,
+            incidents_file: settings.incidents_file,
             services,
+        };
+        Ok((settings, problems))
+    }
+
+    /// The ZMQ endpoints to bind on, either the `endpoints` or the one made of `protocol`, `bind` and `port`
+    pub fn bind_endpoints(&self) -> Vec<String> {
+        if !self.endpoints.is_empty() {
+            return self.endpoints.clone();
         }
+        vec![format!("{}://{}:{}", self.protocol, self.bind, self.port)]
+    }
+
+    /// Returns a copy of the settings safe to show to clients, with only the selected services, redacted (see `Service::redacted`).
+    /// The services are taken out while the rest is copied, so only the selected ones are cloned.
+    ///
+    /// # Arguments
+    ///
+    /// * `selected` - Whether a service is kept in the copy.
+    /// * `raw_output` - Whether the raw output of the last runs is kept.
+    pub fn snapshot(&mut self, selected: impl Fn(&Service) -> bool, raw_output: bool) -> Settings {
+        let services = std::mem::take(&mut self.services);
+        let mut snapshot = self.clone();
+        self.services = services;
+        snapshot.services = self
+            .services
+            .iter()
+            .filter(|service| selected(service))
+            .map(|service| {
+                let mut service = service.redacted();
+                if !raw_output {
+                    service.raw_output = None;
+                }
+                service
+            })
+            .collect();
+        snapshot
+    }
+
+    /// Sums up the services of every group, in the order the groups first appear.
+    ///
+    /// The success score of a group is the average of its services that ran (so not pending or skipped),
+    /// and the status is taken from the global thresholds. A group with an unknown service is unknown.
+    pub fn groups(&self) -> Vec<GroupSummary> {
+        let mut names: Vec<&str> = vec![];
+        for service in &self.services {
+            if let Some(group) = &service.group
+                && !names.contains(&group.as_str())
+            {
+                names.push(group);
+            }
+        }
+
+        names
+            .into_iter()
+            .map(|group| {
+                let members: Vec<&Service> = self
+                    .services
+                    .iter()
+                    .filter(|s| s.group.as_deref() == Some(group))
+                    .collect();
+                let ran: Vec<&&Service> = members
+                    .iter()
+                    .filter(|s| !matches!(s.status, Status::Pending | Status::Skipped))
+                    .collect();
+                let successes = if ran.is_empty() {
+                    None
+                } else {
+                    Some(ran.iter().map(|s| s.successes).sum::<f64>() / ran.len() as f64)
+                };
+                let status = match successes {
+                    _ if ran.iter().any(|s| s.status == Status::Unknown) => Status::Unknown,
+                    Some(successes) => self.thresholds.status_of(successes),
+                    None if members.iter().any(|s| s.status == Status::Pending) => Status::Pending,
+                    None => Status::Skipped,
+                };
+                GroupSummary {
+                    group: group.to_string(),
+                    successes,
+                    status,
+                    services: members.iter().map(|s| s.name.clone()).collect(),
+                    problems: members
+                        .iter()
+                        .filter(|s| s.status.is_problem())
+                        .map(|s| s.name.clone())
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Reads and parses a single settings file, the format being detected by the file extension.
+    fn load_file(path: &str) -> Result<ParsedSettings, Vec<Problem>> {
+        let file_content = match fs::read_to_string(path) {
+            Ok(fc) => fc,
+            Err(error) => {
+                return Err(vec![Problem::error(path, "", None, format!("Unable to open the file: {}", error))]);
+            }
+        };
+        SettingsFormat::from_path(path)
+            .parse(&file_content)
+            .map_err(|(location, message)| vec![Problem::error(path, "", location, message)])
     }
 }
 
@@ -167,17 +546,51 @@ impl fmt::Display for Settings {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Check Interval: {}\n\
+            "Endpoints: {}\n\
+               Check Interval: {}\n\
                Timeout: {}\n\
                Skip with no internet: {}\n\
                Retries: {}\n\
                Retry strategy: {}\n\
+               Max output bytes: {}\n\
+               Stderr is warning: {}\n\
+               Thresholds: {}\n\
+               Partial success: {}\n\
+               CURVE: {}\n\
+               Access control: {}\n\
+               Audit log: {}\n\
+               History: {}\n\
+               Status page: {}\n\
+               Badges: {}\n\
+               Incidents: {}\n\
                Services:\n{}\n",
+            self.bind_endpoints().join(", "),
             self.interval,
             self.timeout,
             self.pause_on_no_internet,
             self.retry_counter,
             self.retry_strategy,
+            self.max_output_bytes,
+            self.stderr_is_warning,
+            self.thresholds,
+            self.partial_success,
+            match &self.curve {
+                Some(curve) if curve.allowed_clients.is_empty() => "enabled, any client".to_string(),
+                Some(curve) => format!("enabled, {} allowed clients", curve.allowed_clients.len()),
+                None => "disabled".to_string(),
+            },
+            match &self.access {
+                Some(access) => format!("{} roles, {} clients", access.roles.len(), access.clients.len()),
+                None => "disabled".to_string(),
+            },
+            self.audit_log.as_deref().unwrap_or("disabled"),
+            match &self.history {
+                Some(history) => format!("{}, kept for {} days", history.file, history.retention_days),
+                None => "disabled".to_string(),
+            },
+            self.status_page.as_ref().map(|page| page.directory.as_str()).unwrap_or("disabled"),
+            self.badges.as_ref().map(|badges| badges.directory.as_str()).unwrap_or("disabled"),
+            self.incidents_file.as_deref().unwrap_or("in memory"),
             self.services
                 .iter()
                 .map(|s| s.to_string())
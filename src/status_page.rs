@@ -0,0 +1,355 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::history::History;
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::utils::partial_success::PartialSuccess;
+use crate::utils::status::{Status, StatusThresholds};
+use crate::utils::html::escape;
+
+/// The fields of a service that can be hidden from the status page
+pub const PUBLIC_FIELDS: &[&str] = &["successes", "last_run", "uptime", "results"];
+
+/// Where the public status page is written to
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StatusPageSettings {
+    pub directory: String,
+    pub title: String,
+    /// How many days the uptime bars go back
+    pub days: u32,
+}
+
+impl StatusPageSettings {
+    /// Parses the `status_page` object of the settings, e.g. `{"directory": "/var/www/status", "title": "Example status"}`
+    pub fn from_value(value: &Value) -> Option<StatusPageSettings> {
+        Some(StatusPageSettings {
+            directory: value.get("directory")?.as_str()?.to_string(),
+            title: value
+                .get("title")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Status")
+                .to_string(),
+            days: value
+                .get("days")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .unwrap_or(90),
+        })
+    }
+}
+
+/// The fields of a public service the status page and its badge are made of,
+/// copied from the settings so the files can be written without holding them
+#[derive(Clone, Debug)]
+pub struct PublicService {
+    pub name: String,
+    pub group: Option<String>,
+    pub public: PublicSettings,
+    pub status: Status,
+    pub successes: f64,
+    pub last_run: Option<DateTime<Utc>>,
+    pub thresholds: StatusThresholds,
+    pub interval: u64,
+    pub partial_success: PartialSuccess,
+    /// Only the test results shown on the page
+    pub tests: Vec<TestResult>,
+}
+
+impl PublicService {
+    /// Copies what is shown of a service, `None` if it has `"public": false`
+    pub fn from_service(service: &Service) -> Option<PublicService> {
+        if !service.public.shown {
+            return None;
+        }
+        let tests = match &service.result {
+            ResultOutput::Result(results) => results
+                .iter()
+                .filter(|r| service.public.shows_test(&r.name))
+                .cloned()
+                .collect(),
+            _ => vec![],
+        };
+        Some(PublicService {
+            name: service.name.clone(),
+            group: service.group.clone(),
+            public: service.public.clone(),
+            status: service.status,
+            successes: service.successes,
+            last_run: service.last_run,
+            thresholds: service.thresholds.clone(),
+            interval: service.interval,
+            partial_success: service.partial_success,
+            tests,
+        })
+    }
+}
+
+/// Copies what is shown of every public service, see `PublicService`
+pub fn public_services(services: &[Service]) -> Vec<PublicService> {
+    services.iter().filter_map(PublicService::from_service).collect()
+}
+
+/// How a service is shown on the public status page
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PublicSettings {
+    /// Whether the service is on the page at all
+    pub shown: bool,
+    /// The name shown instead of the name of the service
+    pub name: Option<String>,
+    /// The test results whose details are shown, `*` for all of them
+    pub tests: Vec<String>,
+    /// The fields left out, see `PUBLIC_FIELDS`
+    pub hide: Vec<String>,
+}
+
+impl PublicSettings {
+    /// Parses the `public` key of a service, either `true`/`false` or `{"name": ..., "tests": [...], "hide": [...]}`.
+    /// Services are shown without the details of their tests if left out.
+    pub fn from_value(value: Option<&Value>) -> PublicSettings {
+        let strings = |key: &str| -> Vec<String> {
+            value
+                .and_then(|v| v.get(key))
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|s| s.as_str().map(String::from)).collect())
+                .unwrap_or_default()
+        };
+        PublicSettings {
+            shown: value.and_then(|v| v.as_bool()).unwrap_or(true),
+            name: value
+                .and_then(|v| v.get("name"))
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            tests: strings("tests"),
+            hide: strings("hide"),
+        }
+    }
+
+    /// Whether a field of the service is shown, see `PUBLIC_FIELDS`
+    pub fn shows(&self, field: &str) -> bool {
+        !self.hide.iter().any(|f| f == field)
+    }
+
+    /// Whether the details of a test result are shown
+    pub fn shows_test(&self, name: &str) -> bool {
+        self.tests.iter().any(|t| t == "*" || t == name)
+    }
+}
+
+/// Renders the status page to `index.html` in the directory of the status page.
+///
+/// The page is a single HTML file with the CSS inlined and no JavaScript, so any static web server can serve it.
+/// It's written to a temporary file first and then moved into place, so visitors never see a half written page.
+/// Callers have to make sure only one page is rendered at a time, e.g. by holding the lock of the history.
+///
+/// # Arguments
+///
+/// * `page` - The status page settings.
+/// * `services` - The current state of the public services, see `public_services`.
+/// * `history` - The run history, for the uptime bars.
+pub fn render(page: &StatusPageSettings, services: &[PublicService], history: &History) -> io::Result<()> {
+    let services: Vec<&PublicService> = services.iter().collect();
+    let now = Utc::now();
+
+    // The services grouped by their group, in the order the groups first appear
+    let mut sections: Vec<(Option<&str>, Vec<&PublicService>)> = vec![];
+    for service in &services {
+        let group = service.group.as_deref();
+        match sections.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, members)) => members.push(service),
+            None => sections.push((group, vec![service])),
+        }
+    }
+    // Services without a group last
+    sections.sort_by_key(|(group, _)| group.is_none());
+
+    let mut body = String::new();
+    let (overall_class, overall_text) = overall(&services);
+    body.push_str(&format!("<div class=\"banner {}\">{}</div>\n", overall_class, overall_text));
+    for (group, members) in &sections {
+        body.push_str("<section>\n");
+        let heading = match group {
+            Some(group) => escape(group),
+            None if sections.len() > 1 => "Other services".to_string(),
+            None => "Services".to_string(),
+        };
+        let worst = worst_status(members);
+        body.push_str(&format!(
+            "<h2>{}<span class=\"status {}\">{}</span></h2>\n",
+            heading,
+            worst.as_str(),
+            label(worst)
+        ));
+        for service in members {
+            body.push_str(&service_html(service, page.days, history, now));
+        }
+        body.push_str("</section>\n");
+    }
+    if services.is_empty() {
+        body.push_str("<p class=\"meta\">No services</p>\n");
+    }
+
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<main>\n<h1>{title}</h1>\n{body}\
+         <footer>Updated {updated}</footer>\n</main>\n</body>\n</html>\n",
+        title = escape(&page.title),
+        style = STYLE,
+        body = body,
+        updated = now.format("%Y-%m-%d %H:%M UTC"),
+    );
+
+    fs::create_dir_all(&page.directory)?;
+    let path = Path::new(&page.directory).join("index.html");
+    let temporary = Path::new(&page.directory).join(".index.html.tmp");
+    fs::write(&temporary, html)?;
+    fs::rename(&temporary, &path)
+}
+
+/// The HTML of a single service, with its uptime bar and public test results
+fn service_html(service: &PublicService, days: u32, history: &History, now: DateTime<Utc>) -> String {
+    let public = &service.public;
+    let name = public.name.as_deref().unwrap_or(&service.name);
+    let mut html = format!(
+        "<div class=\"service\">\n<div class=\"row\"><span class=\"name\">{}</span><span class=\"status {}\">{}</span></div>\n",
+        escape(name),
+        service.status.as_str(),
+        label(service.status)
+    );
+
+    if public.shows("uptime") {
+        html.push_str("<div class=\"bar\">");
+        for (date, day) in history.daily(&service.name, days) {
+            let (class, title) = match day {
+                Some(day) => (
+                    service.thresholds.status_of(day.uptime()).as_str(),
+                    format!("{}: {:.2}% of {} runs", date, day.uptime() * 100.0, day.runs),
+                ),
+                None => ("none", format!("{}: no data", date)),
+            };
+            html.push_str(&format!("<span class=\"day {}\" title=\"{}\"></span>", class, title));
+        }
+        html.push_str("</div>\n");
+        let uptime = match history.uptime(&service.name, days) {
+            Some(uptime) => format!("{:.2}% uptime", uptime * 100.0),
+            None => "no data".to_string(),
+        };
+        html.push_str(&format!(
+            "<div class=\"meta spread\"><span>{} days ago</span><span>{}</span><span>Today</span></div>\n",
+            days, uptime
+        ));
+    }
+
+    let mut details: Vec<String> = vec![];
+    if public.shows("successes") && !matches!(service.status, Status::Pending | Status::Skipped) {
+        details.push(format!("{:.0}% of checks passing", service.successes * 100.0));
+    }
+    if public.shows("last_run")
+        && let Some(last_run) = service.last_run
+    {
+        details.push(format!("checked {} ago", age(now - last_run)));
+    }
+    if !details.is_empty() {
+        html.push_str(&format!("<div class=\"meta\">{}</div>\n", details.join(" · ")));
+    }
+
+    if !service.tests.is_empty() {
+        html.push_str("<ul class=\"tests\">\n");
+        for test in &service.tests {
+            let status = test.status.unwrap_or(service.thresholds.status_of(test.success));
+            let result = match &test.result {
+                _ if !public.shows("results") => String::new(),
+                Value::String(s) => format!(" <span class=\"meta\">{}</span>", escape(s.lines().next().unwrap_or_default())),
+                Value::Null => String::new(),
+                other => format!(" <span class=\"meta\">{}</span>", escape(&other.to_string())),
+            };
+            html.push_str(&format!(
+                "<li><span class=\"dot {}\" title=\"{}\"></span>{}{}</li>\n",
+                status.as_str(),
+                label(status),
+                escape(&test.name),
+                result
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// The class and text of the banner on top of the page
+fn overall(services: &[&PublicService]) -> (&'static str, &'static str) {
+    let statuses: Vec<Status> = services.iter().map(|s| s.status).collect();
+    if statuses.contains(&Status::Critical) {
+        ("critical", "Major outage")
+    } else if statuses.iter().any(|s| s.is_problem()) {
+        ("warning", "Partial outage")
+    } else if statuses.contains(&Status::Ok) {
+        ("ok", "All systems operational")
+    } else {
+        ("pending", "Checking services")
+    }
+}
+
+/// The status of the service needing the most attention
+fn worst_status(services: &[&PublicService]) -> Status {
+    let order = [Status::Critical, Status::Unknown, Status::Warning, Status::Ok, Status::Pending, Status::Skipped];
+    order
+        .into_iter()
+        .find(|status| services.iter().any(|s| s.status == *status))
+        .unwrap_or(Status::Pending)
+}
+
+/// How a status is worded for visitors of the page
+fn label(status: Status) -> &'static str {
+    match status {
+        Status::Ok => "Operational",
+        Status::Warning => "Degraded",
+        Status::Critical => "Outage",
+        Status::Unknown => "Unknown",
+        Status::Pending => "Pending",
+        Status::Skipped => "Not checked",
+    }
+}
+
+/// A short human readable duration, e.g. `42 seconds` or `5 minutes`
+fn age(duration: chrono::TimeDelta) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (amount, unit) = match seconds {
+        0..60 => (seconds, "second"),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+const STYLE: &str = "\
+body{margin:0;background:#f6f7f9;color:#1f2328;font:16px/1.5 system-ui,-apple-system,\"Segoe UI\",sans-serif}\
+main{max-width:52rem;margin:0 auto;padding:2rem 1rem}\
+h1{font-size:1.75rem;margin:0 0 1.5rem}\
+h2{display:flex;justify-content:space-between;font-size:1.1rem;margin:0;padding:1rem;border-bottom:1px solid #e4e7eb}\
+section{background:#fff;border:1px solid #e4e7eb;border-radius:8px;margin-bottom:1.5rem}\
+.banner{padding:1rem;border-radius:8px;color:#fff;font-weight:600;margin-bottom:1.5rem}\
+.banner.ok{background:#2da44e}.banner.warning{background:#d4a72c}.banner.critical{background:#cf222e}.banner.pending{background:#8c959f}\
+.service{padding:1rem;border-bottom:1px solid #eef0f2}.service:last-child{border-bottom:0}\
+.row{display:flex;justify-content:space-between;align-items:baseline}\
+.name{font-weight:600}\
+.status{font-size:.9rem;font-weight:600}\
+.status.ok{color:#1a7f37}.status.warning{color:#9a6700}.status.critical{color:#cf222e}.status.unknown{color:#8250df}\
+.status.pending,.status.skipped{color:#6e7781}\
+.bar{display:flex;gap:2px;height:2rem;margin:.5rem 0 .25rem}\
+.day{flex:1;border-radius:2px;background:#d0d7de}\
+.day.ok{background:#2da44e}.day.warning{background:#d4a72c}.day.critical{background:#cf222e}\
+.meta{color:#6e7781;font-size:.85rem}\
+.spread{display:flex;justify-content:space-between}\
+.tests{list-style:none;margin:.5rem 0 0;padding:0}\
+.tests li{font-size:.9rem}\
+.dot{display:inline-block;width:.6rem;height:.6rem;border-radius:50%;margin-right:.5rem;background:#8c959f}\
+.dot.ok{background:#2da44e}.dot.warning{background:#d4a72c}.dot.critical{background:#cf222e}.dot.unknown{background:#8250df}\
+footer{color:#6e7781;font-size:.85rem;text-align:center}";
@@ -0,0 +1,165 @@
+use serde_json::{json, Value};
+
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::tester::Tester;
+
+impl Tester {
+    /// Parses a single line of JSON Lines output into a `TestResult`.
+    ///
+    /// Every line is expected to be a JSON test object, like in the JSON format.
+    /// Empty lines and lines that aren't JSON (e.g. debug output or a truncation marker) are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A single line of stdout.
+    /// * `service` - The service being tested.
+    pub(crate) fn format_jsonl_line(line: &str, service: &Service) -> Option<TestResult> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        match serde_json::from_str::<Value>(line) {
+            Ok(value) if value.is_object() => Tester::json_test_result(&value, service),
+            _ => {
+                println!("Invalid JSON line in {}, skipping: {}", service.name, line);
+                None
+            }
+        }
+    }
+
+    /// Formats the tests parsed from JSON Lines output into a `ResultOutput`.
+    ///
+    /// The results are kept even when the command failed or was terminated by the timeout,
+    /// adding a failing `exit` test describing why it stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The tests parsed with `format_jsonl_line`.
+    /// * `code` - The exit code of the command, `None` if it was terminated by a signal.
+    /// * `service` - The service being tested.
+    pub(crate) fn format_jsonl(mut results: Vec<TestResult>, code: Option<i32>, service: &Service) -> ResultOutput {
+        if code != Some(0) {
+            let (success, description) = match code {
+                Some(code) => (
+                    service
+                        .exit_code_value(Some(code))
+                        .map(|v| v.to_successes())
+                        .unwrap_or(0.0),
+                    format!("Exited with non-zero code: {}", code),
+                ),
+                None => (0.0, "Terminated by a signal, most likely the timeout".to_string()),
+            };
+            results.push(TestResult {
+                name: "exit".to_string(),
+                success,
+                result: json!(description),
+                status: None,
+            });
+        }
+
+        if !results.is_empty() {
+            ResultOutput::Result(results)
+        } else {
+            ResultOutput::Unknown("No valid tests found in the JSON Lines output".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jsonl_service() -> Service {
+        Service::for_tests(&json!({"name": "smoke", "command": "smoke-tests", "format": "jsonl", "exit_codes": {"1": 0.5}}))
+    }
+
+    fn parse(output: &str) -> Vec<TestResult> {
+        let service = jsonl_service();
+        output.lines().filter_map(|line| Tester::format_jsonl_line(line, &service)).collect()
+    }
+
+    #[test]
+    fn parses_one_test_per_line() {
+        let results = parse(
+            "{\"name\": \"login\", \"success\": 1, \"result\": \"fine\"}\n\
+             {\"name\": \"upload\", \"success\": false, \"result\": {\"code\": 500}}\n",
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "login");
+        assert_eq!(results[0].success, 1.0);
+        assert_eq!(results[1].success, 0.0);
+        assert_eq!(results[1].result, json!({"code": 500}));
+    }
+
+    #[test]
+    fn skips_empty_and_malformed_lines() {
+        let results = parse(
+            "\n   \n\
+             Starting the smoke tests...\n\
+             [1, 2, 3]\n\
+             \"just a string\"\n\
+             {\"name\": \"no success\", \"result\": 1}\n\
+             {\"name\": \"too successful\", \"success\": 2, \"result\": 1}\n\
+             {\"name\": 5, \"success\": 1, \"result\": 1}\n\
+             {\"name\": \"no result\", \"success\": 1}\n\
+             {\"name\": \"login\", \"success\": 0.5, \"result\": null}\n",
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "login");
+        assert_eq!(results[0].success, 0.5);
+    }
+
+    #[test]
+    fn skips_truncated_lines() {
+        // E.g. the command was killed by the timeout halfway through writing a line, or the output was cut off
+        let results = parse(
+            "[... 2048 bytes truncated ...]\n\
+             {\"name\": \"login\", \"success\": 1, \"result\": \"fine\"}\n\
+             {\"name\": \"upload\", \"succ",
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "login");
+    }
+
+    #[test]
+    fn empty_output_is_unknown() {
+        let service = jsonl_service();
+        assert!(parse("").is_empty());
+        let ResultOutput::Unknown(message) = Tester::format_jsonl(vec![], Some(0), &service) else {
+            panic!("expected an unknown result");
+        };
+        assert_eq!(message, "No valid tests found in the JSON Lines output");
+    }
+
+    #[test]
+    fn keeps_the_results_of_a_command_that_stopped_early() {
+        let service = jsonl_service();
+        let results = parse("{\"name\": \"login\", \"success\": 1, \"result\": \"fine\"}\n");
+
+        let ResultOutput::Result(killed) = Tester::format_jsonl(results.clone(), None, &service) else {
+            panic!("expected test results");
+        };
+        assert_eq!(killed.len(), 2);
+        assert_eq!(killed[1].name, "exit");
+        assert_eq!(killed[1].success, 0.0);
+        assert_eq!(killed[1].result, json!("Terminated by a signal, most likely the timeout"));
+
+        // The exit codes of the service still apply
+        let ResultOutput::Result(failed) = Tester::format_jsonl(results.clone(), Some(1), &service) else {
+            panic!("expected test results");
+        };
+        assert_eq!(failed[1].success, 0.5);
+        let ResultOutput::Result(failed) = Tester::format_jsonl(results, Some(2), &service) else {
+            panic!("expected test results");
+        };
+        assert_eq!(failed[1].success, 0.0);
+        assert_eq!(failed[1].result, json!("Exited with non-zero code: 2"));
+
+        // Without any tests, the exit test is all there is
+        let ResultOutput::Result(only_exit) = Tester::format_jsonl(vec![], Some(2), &service) else {
+            panic!("expected test results");
+        };
+        assert_eq!(only_exit.len(), 1);
+    }
+}
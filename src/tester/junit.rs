@@ -0,0 +1,223 @@
+use roxmltree::{Document, Node};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::tester::Tester;
+use crate::utils::status::Status;
+
+impl Tester {
+    /// Formats a JUnit XML report into a `ResultOutput`.
+    ///
+    /// The report is read from the `report` file of the service if set, otherwise from stdout.
+    /// A report file that wasn't written since the command started is considered stale.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdout` - The stdout of the command.
+    /// * `service` - The service being tested.
+    /// * `started` - When the command was started.
+    pub(crate) fn format_junit_report(stdout: &str, service: &Service, started: SystemTime) -> ResultOutput {
+        let Some(path) = &service.report else {
+            return Tester::format_junit(stdout);
+        };
+
+        // Some filesystems only store the modification time in whole seconds
+        let modified = fs::metadata(path).and_then(|m| m.modified());
+        match modified {
+            Ok(modified) if modified + Duration::from_secs(1) >= started => {}
+            Ok(_) => {
+                return ResultOutput::Unknown(format!(
+                    "JUnit report {} wasn't updated by the command",
+                    path
+                ))
+            }
+            Err(e) => return ResultOutput::Unknown(format!("Unable to read JUnit report {}: {}", path, e)),
+        }
+        match fs::read_to_string(path) {
+            Ok(xml) => Tester::format_junit(&xml),
+            Err(e) => ResultOutput::Unknown(format!("Unable to read JUnit report {}: {}", path, e)),
+        }
+    }
+
+    /// Formats JUnit XML into a `ResultOutput`.
+    ///
+    /// Every `<testcase>` becomes a `TestResult` named `classname.name`. Test cases with a `<failure>` or `<error>`
+    /// fail with the message as part of the result, and `<skipped>` test cases are marked as skipped.
+    /// The result also contains the timing of the test case and its test suite.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A string that represents the JUnit XML to be formatted.
+    ///
+    /// # Returns
+    ///
+    /// A `ResultOutput` that represents the formatted result.
+    pub(crate) fn format_junit(value: &str) -> ResultOutput {
+        let document = match Document::parse(value.trim()) {
+            Ok(document) => document,
+            Err(e) => return ResultOutput::Unknown(format!("Invalid JUnit XML: {}", e)),
+        };
+
+        let mut results: Vec<TestResult> = vec![];
+        for testcase in document
+            .descendants()
+            .filter(|n| n.has_tag_name("testcase"))
+        {
+            results.push(Tester::junit_testcase(testcase));
+        }
+
+        if !results.is_empty() {
+            ResultOutput::Result(results)
+        } else {
+            ResultOutput::Unknown("No test cases found in the JUnit XML".to_string())
+        }
+    }
+
+    /// Converts a single `<testcase>` element into a `TestResult`.
+    fn junit_testcase(testcase: Node) -> TestResult {
+        let name = match (testcase.attribute("classname"), testcase.attribute("name")) {
+            (Some(classname), Some(name)) if !classname.is_empty() => format!("{}.{}", classname, name),
+            (_, Some(name)) => name.to_string(),
+            (Some(classname), None) => classname.to_string(),
+            (None, None) => "testcase".to_string(),
+        };
+
+        let mut result = Map::new();
+        if let Some(time) = testcase.attribute("time").and_then(|t| t.parse::<f64>().ok()) {
+            result.insert("time".to_string(), json!(time));
+        }
+        if let Some(suite) = testcase.ancestors().find(|n| n.has_tag_name("testsuite")) {
+            if let Some(suite_name) = suite.attribute("name") {
+                result.insert("suite".to_string(), json!(suite_name));
+            }
+            if let Some(time) = suite.attribute("time").and_then(|t| t.parse::<f64>().ok()) {
+                result.insert("suite_time".to_string(), json!(time));
+            }
+            if let Some(timestamp) = suite.attribute("timestamp") {
+                result.insert("suite_timestamp".to_string(), json!(timestamp));
+            }
+        }
+
+        let failure = testcase
+            .children()
+            .find(|n| n.has_tag_name("failure") || n.has_tag_name("error"));
+        let skipped = testcase.children().find(|n| n.has_tag_name("skipped"));
+
+        let (success, status, detail) = if let Some(failure) = failure {
+            (0.0, None, Some(failure))
+        } else if let Some(skipped) = skipped {
+            (1.0, Some(Status::Skipped), Some(skipped))
+        } else {
+            (1.0, None, None)
+        };
+
+        if let Some(detail) = detail {
+            if let Some(message) = detail.attribute("message") {
+                result.insert("message".to_string(), json!(message));
+            }
+            if let Some(kind) = detail.attribute("type") {
+                result.insert("type".to_string(), json!(kind));
+            }
+            let text = detail.text().map(str::trim).unwrap_or("");
+            if !text.is_empty() {
+                result.insert("details".to_string(), json!(text));
+            }
+        }
+
+        TestResult {
+            name,
+            success,
+            result: Value::Object(result),
+            status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(xml: &str) -> Vec<TestResult> {
+        match Tester::format_junit(xml) {
+            ResultOutput::Result(results) => results,
+            other => panic!("expected test results, got {:?}", other),
+        }
+    }
+
+    fn unknown(xml: &str) -> String {
+        match Tester::format_junit(xml) {
+            ResultOutput::Unknown(message) => message,
+            other => panic!("expected an unknown result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_passing_failing_and_skipped_test_cases() {
+        let results = results(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <testsuites>
+              <testsuite name="api" time="1.5" timestamp="2024-05-01T12:00:00">
+                <testcase classname="api.health" name="responds" time="0.2"/>
+                <testcase classname="api.login" name="rejects bad passwords" time="0.3">
+                  <failure message="expected 401" type="AssertionError">got 200</failure>
+                </testcase>
+                <testcase name="uploads"><skipped message="no bucket"/></testcase>
+                <testcase classname="" name="errors"><error message="boom"/></testcase>
+              </testsuite>
+            </testsuites>"#,
+        );
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["api.health.responds", "api.login.rejects bad passwords", "uploads", "errors"]);
+
+        assert_eq!(results[0].success, 1.0);
+        assert_eq!(
+            results[0].result,
+            json!({"time": 0.2, "suite": "api", "suite_time": 1.5, "suite_timestamp": "2024-05-01T12:00:00"})
+        );
+        assert_eq!(results[1].success, 0.0);
+        assert_eq!(results[1].result["message"], json!("expected 401"));
+        assert_eq!(results[1].result["type"], json!("AssertionError"));
+        assert_eq!(results[1].result["details"], json!("got 200"));
+        assert_eq!(results[2].success, 1.0);
+        assert_eq!(results[2].status, Some(Status::Skipped));
+        assert_eq!(results[3].success, 0.0);
+    }
+
+    #[test]
+    fn names_test_cases_without_a_name() {
+        let results = results(r#"<testsuite><testcase classname="only.class"/><testcase/></testsuite>"#);
+        assert_eq!(results[0].name, "only.class");
+        assert_eq!(results[1].name, "testcase");
+        // Without a test suite attribute or timing there is nothing else to show
+        assert_eq!(results[1].result, json!({}));
+    }
+
+    #[test]
+    fn empty_documents_are_unknown() {
+        assert!(unknown("").starts_with("Invalid JUnit XML"));
+        assert!(unknown("   \n").starts_with("Invalid JUnit XML"));
+        assert_eq!(unknown("<testsuites/>"), "No test cases found in the JUnit XML");
+        assert_eq!(
+            unknown(r#"<testsuite name="api" tests="0"></testsuite>"#),
+            "No test cases found in the JUnit XML"
+        );
+    }
+
+    #[test]
+    fn malformed_and_truncated_documents_are_unknown() {
+        // E.g. the test runner got killed while writing the report
+        assert!(unknown(r#"<testsuite name="api"><testcase name="responds"/><testca"#).starts_with("Invalid JUnit XML"));
+        assert!(unknown(r#"<testsuite><testcase name="a"></testsuite>"#).starts_with("Invalid JUnit XML"));
+        assert!(unknown("Running 3 tests... done").starts_with("Invalid JUnit XML"));
+        assert!(unknown(r#"{"tests": []}"#).starts_with("Invalid JUnit XML"));
+    }
+
+    #[test]
+    fn ignores_unparsable_timings() {
+        let results = results(r#"<testsuite name="api" time="soon"><testcase name="a" time="1,5"/></testsuite>"#);
+        assert_eq!(results[0].result, json!({"suite": "api"}));
+    }
+}
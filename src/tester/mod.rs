@@ -0,0 +1,770 @@
+use libc;
+use process_alive::Pid;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Instant, SystemTime};
+use std::sync::{Mutex, PoisonError};
+use std::{io, thread, time};
+use std::collections::HashMap;
+use crate::service::Service;
+use crate::settings::{RawOutput, ResultOutput, TestResult};
+use crate::utils::retry_strategy::RetryStrategy;
+use crate::utils::exit_codes::{ExitCodeKeyword, ExitCodeValue};
+use crate::utils::output_format::OutputFormat;
+use crate::utils::ring_buffer::RingBuffer;
+use crate::utils::status::Status;
+
+mod jsonl;
+mod junit;
+mod nagios;
+mod tap;
+
+type SuccessResult = (f64, ResultOutput);
+
+/// The tests parsed from JSON Lines output while the command is still running
+#[derive(Default)]
+struct Streamed {
+    results: Vec<TestResult>,
+    /// The bytes of the lines kept in `results`, limited by `max_output_bytes` like the rest of the output
+    bytes: usize,
+    /// The tests that didn't fit anymore
+    dropped: usize,
+    /// When the results so far were last published
+    published: Option<Instant>,
+}
+
+pub struct Tester {}
+
+impl Tester {
+    /// The highest success score a run can get when it writes to stderr and `stderr_is_warning` is enabled
+    const STDERR_WARNING_SUCCESSES: f64 = 0.5;
+
+    /// The most tests kept from JSON Lines output, on top of the `max_output_bytes` limit
+    const MAX_STREAMED_RESULTS: usize = 10_000;
+
+    /// How often the results of a running JSON Lines command are published at most, as every result is copied each time
+    const PUBLISH_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
+    /// Tests a service and returns the status, the success rate and the result of the test.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - A `Service` instance that represents the service to be tested.
+    /// * `publish` - Called with partial results while the test is still running, for formats that support it.
+    ///
+    /// # Returns
+    ///
+    /// A tuple where the first element is the `Status` derived from the service thresholds,
+    /// the second element is the success rate and the result of the test as a `SuccessResult`,
+    /// and the last element is the raw output of the last attempt, if the command could be run at all.
+    pub fn test(service: &Service, publish: &(dyn Fn(&ResultOutput) + Sync)) -> (Status, SuccessResult, Option<RawOutput>) {
+        let ((successes, mut result), raw_output) = Tester::run(service, publish);
+        let status = result.evaluate_status(successes, &service.thresholds);
+        (status, (successes, result), raw_output)
+    }
+
+    /// Runs the command of a service, retrying according to its `retry_counter` and `retry_strategy`.
+    fn run(service: &Service, publish: &(dyn Fn(&ResultOutput) + Sync)) -> (SuccessResult, Option<RawOutput>) {
+        let mut command = Command::new(service.command.clone());
+        if let Some(args) = &service.args {
+            command.args(args);
+        }
+        command.envs(&service.env);
+        
+        let mut results: Vec<SuccessResult> = vec!();
+        let mut raw_output: Option<RawOutput> = None;
+        
+        let retries = service.retry_counter;
+        for retry_count in 0..=retries {
+            let started = SystemTime::now();
+
+            // JSON Lines are parsed while the command is running, publishing the results found so far
+            let streamed: Mutex<Streamed> = Mutex::new(Streamed::default());
+            let on_line = |line: &str| {
+                let Some(test_result) = Tester::format_jsonl_line(line, service) else {
+                    return;
+                };
+                let mut streamed = streamed.lock().unwrap_or_else(PoisonError::into_inner);
+                if streamed.results.len() >= Tester::MAX_STREAMED_RESULTS
+                    || (service.max_output_bytes > 0 && streamed.bytes + line.len() > service.max_output_bytes)
+                {
+                    streamed.dropped += 1;
+                    return;
+                }
+                streamed.bytes += line.len();
+                streamed.results.push(test_result);
+                if streamed.published.is_none_or(|published| published.elapsed() >= Tester::PUBLISH_INTERVAL) {
+                    streamed.published = Some(Instant::now());
+                    publish(&ResultOutput::Result(streamed.results.clone()));
+                }
+            };
+            let line_handler: Option<&(dyn Fn(&str) + Sync)> = match service.format {
+                OutputFormat::Jsonl => Some(&on_line),
+                _ => None,
+            };
+
+            let option_output = match command
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+            {
+                Ok(child) => {
+                    let id = child.id();
+                    let timeout = service.timeout;
+                    thread::spawn(move || Tester::suicide_watch(id, timeout));
+                    println!("   {}pid {}", id, service.name);
+                    Tester::capture_output(child, service.max_output_bytes, line_handler)
+                }
+                Err(e) => Err(e),
+            };
+
+            let (status, raw) = match option_output {
+                Ok(output) => output,
+                Err(e) => {
+                    let err_msg = format!("Internal error: {}", e);
+                    let success_result = (0.0, ResultOutput::Unknown(err_msg.to_string()));
+                    if retries > 0 {
+                        if retries > retry_count {
+                            eprintln!("?⟳ {:.2} {} {}", 0.00, service.name, err_msg);
+                        } else {
+                            eprintln!("?  {:.2} {} {}", 0.00, service.name, err_msg);
+                        }
+                        results.push(success_result);
+                        continue;
+                    } else {
+                        eprintln!("?  {:.2} {} {}", 0.00, service.name, err_msg);
+                        return (success_result, raw_output);
+                    }
+                }
+            };
+            raw_output = Some(raw.clone());
+            let stdout = raw.stdout;
+            let stderr = raw.stderr;
+
+            // Command returned a non-zero code
+            if !service.format.handles_exit_code() && !status.success() {
+                let text = if !stderr.is_empty() {
+                    stderr.to_string()
+                } else if !stdout.is_empty() {
+                    stdout.to_string()
+                } else if status.code().is_some() {
+                    format!(
+                        "Exited with non-zero code: {}",
+                        status.code().unwrap_or(9999)
+                    )
+                } else {
+                    status.to_string()
+                };
+                let err_msg = format!("Non-zero exit code: {}", status.code().unwrap_or(2522));
+                // The exit code might be mapped to a (partial) success score, or mean that the outcome is unknown
+                let exit_code_value = service.exit_code_value(status.code());
+                let successes = exit_code_value
+                    .as_ref()
+                    .map(|v| v.to_successes())
+                    .unwrap_or(0.0);
+                let result = match exit_code_value {
+                    Some(ExitCodeValue::Keyword(ExitCodeKeyword::Unknown)) => ResultOutput::Unknown(text),
+                    _ => ResultOutput::String(text),
+                };
+                let success_result = (successes, result);
+                if retries > 0 {
+                    if retries > retry_count {
+                        eprintln!("X⟳ {:.2} {} {}", successes, service.name, err_msg);
+                    } else {
+                        eprintln!("X  {:.2} {} {}", successes, service.name, err_msg);
+                    }
+                    results.push(success_result);
+                    continue;
+                } else {
+                    eprintln!("X  {:.2} {} {}", successes, service.name, err_msg);
+                    return (success_result, raw_output);
+                }
+            }
+
+            let streamed = streamed.into_inner().unwrap_or_else(PoisonError::into_inner);
+            if streamed.dropped > 0 {
+                eprintln!(
+                    "Kept the first {} tests of {}, dropped {} more over the limit",
+                    streamed.results.len(),
+                    service.name,
+                    streamed.dropped
+                );
+            }
+            let (mut successes, result) = Tester::format_output(&stdout, status.code(), service, started, streamed.results);
+
+            // Anything written to stderr on a successful run is treated as a warning, if enabled
+            if service.stderr_is_warning && !stderr.trim().is_empty() && successes > Tester::STDERR_WARNING_SUCCESSES {
+                successes = Tester::STDERR_WARNING_SUCCESSES;
+            }
+
+            if 1.0 > successes {
+                let mut icons = String::new();
+                if successes > 0.5 {
+                    icons += "↑";
+                } else {
+                    icons += "↓";
+                }
+                if retries > retry_count {
+                    icons += "⟳";
+                } else {
+                    icons += " ";
+                }
+                println!("{} {:.2} {}", icons, successes, service.name);
+            }
+            
+            if retries == 0 {
+                // Return early skipping expensive vector push operation
+                // Also skipping the entire retry_strategy logic
+                return ((successes, result), raw_output);
+            } else if successes == 1.0
+                && (service.retry_strategy == RetryStrategy::Best
+                || service.retry_strategy == RetryStrategy::CombinedBest
+                || service.retry_strategy == RetryStrategy::Median
+            ) {
+                // If we already have a success, and we're looking for the best result(s), just return without continuing
+                return ((successes, result), raw_output);
+            } else {
+                results.push((successes, result));   
+            }
+        }
+        
+        (Tester::combine_results(results, &service.retry_strategy), raw_output)
+    }
+
+    /// Formats the output of a successful run according to the `format` of the service.
+    ///
+    /// With `auto`, the format is guessed by trying JSON, TAP and plain text in that order.
+    /// Any other format is forced, meaning output that doesn't match it results in `ResultOutput::Unknown`
+    /// with a description of what went wrong, instead of falling back to another format.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdout` - The stdout of the command.
+    /// * `code` - The exit code of the command, `None` if it was terminated by a signal.
+    /// * `service` - The service being tested.
+    /// * `started` - When the command was started.
+    /// * `streamed` - The tests parsed from JSON Lines while the command was running, empty for any other format.
+    fn format_output(
+        stdout: &str,
+        code: Option<i32>,
+        service: &Service,
+        started: SystemTime,
+        streamed: Vec<TestResult>,
+    ) -> SuccessResult {
+        let result = match service.format {
+            OutputFormat::Auto => {
+                // We want to support 3 different formats. Here we go
+                match serde_json::from_str::<Value>(stdout) {
+                    // JSON, a bare JSON number or string is just plain text
+                    Ok(value) if value.is_object() || value.is_array() => Tester::format_json(value, service),
+                    // TAP
+                    _ if Tester::is_tap(stdout) => Tester::format_tap(stdout),
+                    // PLAIN
+                    _ => Tester::format_plain(stdout),
+                }
+            }
+            OutputFormat::Json => match serde_json::from_str::<Value>(stdout) {
+                Ok(value) => Tester::format_json(value, service),
+                Err(e) => ResultOutput::Unknown(format!("Invalid JSON output: {}", e)),
+            },
+            OutputFormat::Plain => match Tester::format_plain(stdout) {
+                ResultOutput::Result(results) => ResultOutput::Result(results),
+                _ => ResultOutput::Unknown(
+                    "No name-success-description tests found in the plain output".to_string(),
+                ),
+            },
+            OutputFormat::ExitCode => {
+                // Non-zero exit codes never get this far, the output is only kept as information
+                if stdout.is_empty() {
+                    ResultOutput::Null
+                } else {
+                    ResultOutput::String(stdout.to_string())
+                }
+            }
+            // Parsed while the command was running, as the stdout kept might not hold every line anymore
+            OutputFormat::Jsonl => Tester::format_jsonl(streamed, code, service),
+            OutputFormat::Tap => Tester::check_tap_exit_code(Tester::format_tap(stdout), code),
+            OutputFormat::Junit => Tester::format_junit_report(stdout, service, started),
+            // The success score of Nagios plugins comes from the exit code, not from the result
+            OutputFormat::Nagios => return Tester::format_nagios(stdout, code, service),
+        };
+        (result.to_successes(), result)
+    }
+
+    /// Waits for a child process to finish while streaming its stdout and stderr into ring buffers.
+    ///
+    /// Unlike `Child::wait_with_output`, this never holds more than `max_output_bytes` of each stream in memory.
+    /// Only the last `max_output_bytes` are kept, with a truncation marker in front if anything was discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The spawned child process, with piped stdout and stderr.
+    /// * `max_output_bytes` - The maximum amount of bytes to keep per stream. `0` means unlimited.
+    /// * `on_line` - Called with every line written to stdout as soon as it is complete, if set.
+    ///   Lines longer than `max_output_bytes` are discarded.
+    ///
+    /// # Returns
+    ///
+    /// The exit status of the process together with the captured `RawOutput`.
+    fn capture_output(
+        mut child: Child,
+        max_output_bytes: usize,
+        on_line: Option<&(dyn Fn(&str) + Sync)>,
+    ) -> io::Result<(ExitStatus, RawOutput)> {
+        fn read_stream<R: Read>(
+            stream: Option<R>,
+            pid: u32,
+            max_output_bytes: usize,
+            on_line: Option<&(dyn Fn(&str) + Sync)>,
+        ) -> RingBuffer {
+            let mut buffer = RingBuffer::new(max_output_bytes);
+            // Only used to split the stream into lines when `on_line` is set
+            let mut line: Vec<u8> = vec![];
+            // Longer lines are discarded, so output without any newlines can't grow `line` forever
+            let max_line_bytes = if max_output_bytes > 0 { max_output_bytes } else { usize::MAX };
+            let mut discarding = false;
+            if let Some(mut stream) = stream {
+                let mut chunk = [0u8; 8192];
+                loop {
+                    match stream.read(&mut chunk) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            buffer.push(&chunk[..n]);
+                            if let Some(on_line) = on_line {
+                                for byte in &chunk[..n] {
+                                    if *byte == b'\n' {
+                                        if !discarding {
+                                            on_line(&String::from_utf8_lossy(&line));
+                                        }
+                                        line.clear();
+                                        discarding = false;
+                                    } else if discarding {
+                                        continue;
+                                    } else if line.len() >= max_line_bytes {
+                                        eprintln!("Discarding a line of more than {} bytes from {}", max_line_bytes, pid);
+                                        line.clear();
+                                        discarding = true;
+                                    } else {
+                                        line.push(*byte);
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => {
+                            // Nothing drains the pipe anymore, so the command would block forever once it's full
+                            eprintln!("Unable to read the output of {}, killing it: {}", pid, e);
+                            unsafe {
+                                libc::kill(pid as i32, 9);
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+            if let (Some(on_line), false) = (on_line, line.is_empty() || discarding) {
+                on_line(&String::from_utf8_lossy(&line));
+            }
+            buffer
+        }
+
+        let pid = child.id();
+        let stdout_pipe = child.stdout.take();
+        let stderr_pipe = child.stderr.take();
+        let (status, stdout, stderr) = thread::scope(|scope| {
+            let stdout_handle = scope.spawn(|| read_stream(stdout_pipe, pid, max_output_bytes, on_line));
+            let stderr_handle = scope.spawn(|| read_stream(stderr_pipe, pid, max_output_bytes, None));
+
+            let status = child.wait();
+            let stdout = stdout_handle.join().map_err(|_| io::Error::other("stdout reader panicked"));
+            let stderr = stderr_handle.join().map_err(|_| io::Error::other("stderr reader panicked"));
+            (status, stdout, stderr)
+        });
+        let (status, stdout, stderr) = (status?, stdout?, stderr?);
+
+        Ok((
+            status,
+            RawOutput {
+                stdout: stdout.into_string(),
+                stderr: stderr.into_string(),
+                exit_code: status.code(),
+            },
+        ))
+    }
+
+    /// Formats a JSON value into a `ResultOutput`.
+    ///
+    /// This function expects the JSON value to be either an object or an array. Each object should have the following keys:
+    /// * "name" (string): the name of the test.
+    /// * "success" (number): the success rate of the test. It should be a number between 0.00 and 1.00.
+    /// * "result" (any): the result of the test.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A `Value` that represents the JSON value to be formatted.
+    /// * `test` - A `Service` instance that represents the service being tested.
+    ///
+    /// # Returns
+    ///
+    /// A `ResultOutput` that represents the formatted result,
+    /// or `ResultOutput::Unknown` if the JSON value is neither an object nor an array.
+    fn format_json(value: Value, test: &Service) -> ResultOutput {
+        // JSON
+        // Must include name (string), success(bool|float), result(string|number|bool|json)
+        // Root JSON can be an array or an object
+        let tests: Vec<Value> = match value {
+            Value::Object(obj) => {
+                // Check if key array
+                if obj.iter().all(|(_, v)| v.is_object()) {
+                    let mut vec = Vec::new();
+                    for (k, v) in obj {
+                        let mut new_obj = v;
+                        new_obj["name"] = json!(k);
+                        vec.push(new_obj);
+                    }
+                    vec
+                } else {
+                    vec![Value::Object(obj)]
+                }
+            }
+            Value::Array(arr) => arr,
+            other => {
+                return ResultOutput::Unknown(format!(
+                    "JSON output must be an object or an array, got: {}",
+                    other
+                ))
+            }
+        };
+        let results: Vec<TestResult> = tests
+            .iter()
+            .filter_map(|obj| Tester::json_test_result(obj, test))
+            .collect();
+
+        if !results.is_empty() {
+            ResultOutput::Result(results)
+        } else {
+            ResultOutput::Unknown("No valid tests found in the JSON output".to_string())
+        }
+    }
+
+    /// Converts a single JSON test object into a `TestResult`.
+    ///
+    /// The object requires a "name" (string), "success" (number between 0.00 and 1.00, or bool) and "result" (any) key.
+    /// Invalid objects are reported and skipped by returning `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - The JSON test object.
+    /// * `test` - A `Service` instance that represents the service being tested.
+    fn json_test_result(obj: &Value, test: &Service) -> Option<TestResult> {
+        let name = if let Some(name_value) = obj.get("name") {
+            if let Some(name_str) = name_value.as_str() {
+                name_str.to_string()
+            } else {
+                println!(
+                    "Invalid format in test object {}; Name must be a string, skipping",
+                    test.name
+                );
+                return None;
+            }
+        } else {
+            println!(
+                "Invalid format in test object {}; JSON test require a \"name\" key, skipping",
+                test.name
+            );
+            return None;
+        };
+
+        let success: f64 = match obj.get("success") {
+            Some(Value::Number(n)) if n.as_f64().is_some() => n.as_f64().unwrap_or(0.0),
+            Some(Value::Bool(b)) => *b as i32 as f64,
+            _ => {
+                println!("Invalid format in test object {} -> {}; JSON tests require a \"success\" key with a number value (between 0.00 and 1.00), skipping", test.name, name);
+                return None;
+            }
+        };
+        if !(0.00..=1.00).contains(&success) {
+            println!("Invalid format in test object {} -> {}; JSON tests require the \"success\" key to be a number between 0.00 and 1.00 (or a boolean), skipping", test.name, name);
+            return None;
+        }
+
+        let result_output = if let Some(result_value) = obj.get("result") {
+            result_value
+        } else {
+            println!(
+                "Invalid format in test object {} -> {}; JSON test require a \"result\" key",
+                test.name, name
+            );
+            return None;
+        };
+
+        Some(TestResult {
+            name,
+            success,
+            result: result_output.clone(),
+            status: None,
+        })
+    }
+
+    /// Formats a plain text value into a `ResultOutput`.
+    ///
+    /// This function expects the plain text value to be in the following format:
+    /// * The first line should be the name of the test.
+    /// * The second line should be the success rate of the test. It should be a bool, or a number (int or float) between 0.00 and 1.00.
+    /// * The remaining lines should be the description of the test.
+    ///
+    /// Each test should be separated by an empty line.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A string that represents the plain text value to be formatted.
+    ///
+    /// # Returns
+    ///
+    /// A `ResultOutput` that represents the formatted result.
+    fn format_plain(value: &str) -> ResultOutput {
+        let mut results: Vec<TestResult> = vec![];
+        // Validate format
+        let mut found_name = false;
+        let mut name: String = String::from("");
+        let mut success: f64 = -1.0;
+        let mut result_builder: String = String::from("");
+
+        fn make_result(name: String, success: f64, result_builder: String) -> TestResult {
+            // We want to support 2 different formats. Here we go
+            let result = match serde_json::from_str::<Value>(&result_builder) {
+                // JSON
+                Ok(value) => value,
+                // PLAIN
+                Err(_) => Value::String(result_builder),
+            };
+
+            TestResult {
+                name,
+                success,
+                result,
+                status: None,
+            }
+        }
+
+        for line in value.lines() {
+            if line.is_empty() && success != -1.0 {
+                results.push(make_result(name, success, result_builder));
+                found_name = false;
+                name = String::from("");
+                result_builder = String::from("");
+                success = -1.0;
+                continue;
+            }
+            if !found_name {
+                // Name
+                name = line.to_string();
+                found_name = true;
+                continue;
+            }
+
+            // Success, numbers outside of 0.00 and 1.00 (or NaN) are part of the description instead
+            if let Some(parsed) = line.parse::<f64>().ok().filter(|p| (0.00..=1.00).contains(p)) {
+                success = parsed;
+                continue;
+            }
+            if let Ok(parsed) = line.parse::<bool>() {
+                success = parsed as i32 as f64;
+                continue;
+            }
+
+            // Result
+            result_builder += line;
+        }
+
+        if success != -1.0 {
+            results.push(make_result(name, success, result_builder));
+        }
+
+        if !results.is_empty() {
+            ResultOutput::Result(results)
+        } else if !value.is_empty() {
+            ResultOutput::String(value.to_string())
+        } else {
+            ResultOutput::Null
+        }
+    }
+
+    /// Combines multiple `SuccessResult` into a single combined `SuccessResult` based on the `RetryStrategy` strategy set
+    ///
+    /// Strategy:
+    /// - The `retry_strategy` determines which attempt(s) are shown and how the final
+    ///   score is computed (e.g., last attempt, best score, worst score, or averaged).
+    /// - Ties and empty inputs are handled deterministically.
+    ///
+    /// Arguments:
+    /// - `results`: Multiple results from the same test accumulated by multiple retries.
+    /// - `retry_strategy`: Policy that controls how to merge the results.
+    /// 
+    /// Returns:
+    /// - `(score, ResultOutput)`: The aggregated success score and the merged `ResultOutput`.
+    fn combine_results(results: Vec<SuccessResult>, retry_strategy: &RetryStrategy) -> SuccessResult {
+        // Only happens with a negative retry_counter
+        if results.is_empty() {
+            return (0.0, ResultOutput::Unknown("The test never ran, retry_counter must be 0 or more".to_string()));
+        }
+        match retry_strategy {
+            // This just grabs the best result found
+            RetryStrategy::Best => {
+                let mut best_result = &results[0];
+                for result in &results {
+                    let (s, _) = result;
+                    if s > &best_result.0 {
+                        best_result = result;
+                    }
+                }
+                best_result.to_owned()
+            },
+            // Most relevant when using `ResultOutput::Result(Vec<TestResult>)`.
+            // It will combine all the TestResults and find the best test for each vector.
+            RetryStrategy::CombinedBest => {
+                
+                // The combined_best flag does not make sense if all the Vec<TestResult> are empty or not set.
+                // Therefore, default back to flag "best" if this is the case.
+                if results.iter().all(|(_, r)| match r {
+                    ResultOutput::Result(v) => v.is_empty(),
+                    _ => true,
+                }) {
+                    let mut best_result = &results[0];
+                    for result in &results {
+                        let (s, _) = result;
+                        if s > &best_result.0 {
+                            best_result = result;
+                        }
+                    }
+                    return best_result.to_owned();
+                }
+                
+                let mut best_results: HashMap<String, TestResult> = HashMap::new();
+                for (_s, result) in results {
+                    match result {
+                        ResultOutput::Result(v) => {
+                            for tr in v {
+                                let key = tr.name.clone();
+                                if let Some(best_result) = best_results.get(&key) {
+                                    if tr.success > best_result.success {
+                                        best_results.insert(key, tr);
+                                    }
+                                } else {
+                                    best_results.insert(key, tr); 
+                                }
+                            }
+                        },
+                        _ => continue,
+                    }
+                }
+                let result: ResultOutput = ResultOutput::Result(best_results.values().cloned().collect());
+                let successes = result.to_successes();
+                (successes, result)
+                
+            },
+            RetryStrategy::Median => {
+                // Orders by success rate and picks the middle result
+                // This is generally a bit of a heavy operation to do
+                let count = results.len();
+                let mut results = results.to_owned();
+                results.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mid = count / 2;
+                results[mid].to_owned()
+            },
+            // This just grabs the worst result found
+            RetryStrategy::Worst => {
+                let mut worst_result = &results[0];
+                for result in &results {
+                    let (s, _) = result;
+                    if &worst_result.0 > s {
+                        worst_result = result;
+                    }
+                }
+                worst_result.to_owned()
+            },
+            // Most relevant when using `ResultOutput::Result(Vec<TestResult>)`.
+            // It will combine all the TestResults and find the worst test for each vector, combining them into one.
+            RetryStrategy::CombinedWorst => {
+
+                // The combined_best flag does not make sense if all the Vec<TestResult> are empty or not set.
+                // Therefore, default back to flag "best" if this is the case.
+                if results.iter().all(|(_, r)| match r {
+                    ResultOutput::Result(v) => v.is_empty(),
+                    _ => true,
+                }) {
+                    let mut worst_result = &results[0];
+                    for result in &results {
+                        let (s, _) = result;
+                        if &worst_result.0 > s {
+                            worst_result = result;
+                        }
+                    }
+                    return worst_result.to_owned();
+                }
+
+                // We use a hashmap, and use the test result name as the key. This is unique for each test.
+                let mut worst_results: HashMap<String, TestResult> = HashMap::new();
+                for (_s, result) in results {
+                    match result {
+                        ResultOutput::Result(v) => {
+                            for tr in v {
+                                let key = tr.name.clone();
+                                if let Some(worst_result) = worst_results.get(&key) {
+                                    if worst_result.success > tr.success {
+                                        worst_results.insert(key, tr);
+                                    }
+                                } else {
+                                    worst_results.insert(key, tr);
+                                }
+                            }
+                        },
+                        _ => continue,
+                    }
+                }
+                let result: ResultOutput = ResultOutput::Result(worst_results.values().cloned().collect());
+                let successes = result.to_successes();
+                (successes, result)
+            },
+        }
+    }
+
+    /// Monitors a process and terminates it if it exceeds a specified timeout.
+    ///
+    /// This function will first put the current thread to sleep for the duration of the timeout.
+    /// After waking up, it will check if the process is still alive.
+    /// If it is, it will attempt to terminate the process.
+    /// If the process is still alive after another delay (three times the original timeout), it will forcefully kill the process.
+    ///
+    /// # Arguments
+    ///
+    /// * `pid_num` - The PID of the process to be monitored.
+    /// * `timeout` - The duration (in seconds) to wait before terminating the process.
+    ///
+    /// # Safety
+    ///
+    /// This function is `unsafe` because it calls `libc::kill`, which can lead to undefined behavior if not used correctly.
+    fn suicide_watch(pid_num: u32, timeout: f64) {
+        thread::sleep(time::Duration::from_secs_f64(timeout));
+
+        // Check if exists
+        let pid = Pid::from(pid_num);
+        if process_alive::state(pid).is_alive() {
+            // Termination
+            unsafe {
+                libc::kill(pid_num as i32, 15);
+                println!("Process timeout {}s, terminating {}", timeout, pid_num);
+            }
+        }
+
+        thread::sleep(time::Duration::from_secs_f64(timeout * 3.0));
+        if process_alive::state(pid).is_alive() {
+            // DIE DIE DIE
+            unsafe {
+                libc::kill(pid_num as i32, 9);
+                println!("Failed to terminate. Force killing process {}", pid_num);
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use serde_json::{json, Value};
+
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::tester::{SuccessResult, Tester};
+use crate::utils::exit_codes::{ExitCodeKeyword, ExitCodeValue};
+use crate::utils::status::Status;
+
+/// A Nagios threshold range, e.g. `10`, `10:`, `~:10`, `10:20` or `@10:20`.
+///
+/// See https://nagios-plugins.org/doc/guidelines.html#THRESHOLDFORMAT
+struct NagiosRange {
+    start: f64,
+    end: f64,
+    inside: bool,
+}
+
+impl NagiosRange {
+    fn parse(range: &str) -> Option<NagiosRange> {
+        let range = range.trim();
+        if range.is_empty() {
+            return None;
+        }
+        let (inside, range) = match range.strip_prefix('@') {
+            Some(r) => (true, r),
+            None => (false, range),
+        };
+        let (start, end) = match range.split_once(':') {
+            Some((start, end)) => {
+                let start = match start {
+                    "~" => f64::NEG_INFINITY,
+                    "" => 0.0,
+                    s => s.parse().ok()?,
+                };
+                let end = match end {
+                    "" => f64::INFINITY,
+                    e => e.parse().ok()?,
+                };
+                (start, end)
+            }
+            None => (0.0, range.parse().ok()?),
+        };
+        Some(NagiosRange { start, end, inside })
+    }
+
+    /// Whether the value should raise an alert
+    fn alerts(&self, value: f64) -> bool {
+        let within = self.start <= value && value <= self.end;
+        within == self.inside
+    }
+}
+
+impl Tester {
+    /// Formats the output of a Nagios plugin into a `SuccessResult`.
+    ///
+    /// The first line is expected to be `TEXT | perfdata`, optionally followed by long text lines,
+    /// where a line containing `|` starts more perfdata till the end of the output.
+    ///
+    /// The success score is taken from the exit code (see `Service::exit_code_value`), while the
+    /// result contains a `status` test with the plugin text, followed by one test per perfdata label.
+    /// A perfdata test fails if its value is within the critical threshold, and gets 0.5 if within the warning threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The stdout of the plugin.
+    /// * `code` - The exit code of the plugin, `None` if it was terminated by a signal.
+    /// * `service` - The service being tested.
+    pub(crate) fn format_nagios(value: &str, code: Option<i32>, service: &Service) -> SuccessResult {
+        let exit_code_value = service.exit_code_value(code);
+        let successes = exit_code_value
+            .as_ref()
+            .map(|v| v.to_successes())
+            .unwrap_or(0.0);
+
+        let mut lines = value.lines();
+        let (text, mut perfdata) = match lines.next() {
+            Some(line) => match line.split_once('|') {
+                Some((text, perf)) => (text.trim().to_string(), perf.trim().to_string()),
+                None => (line.trim().to_string(), String::new()),
+            },
+            None => (String::new(), String::new()),
+        };
+
+        let mut long_text: Vec<&str> = vec![];
+        let mut in_perfdata = false;
+        for line in lines {
+            if in_perfdata {
+                perfdata.push(' ');
+                perfdata.push_str(line.trim());
+            } else if let Some((text, perf)) = line.split_once('|') {
+                long_text.push(text);
+                perfdata.push(' ');
+                perfdata.push_str(perf.trim());
+                in_perfdata = true;
+            } else {
+                long_text.push(line);
+            }
+        }
+
+        let mut status_text = text;
+        if !long_text.is_empty() {
+            status_text = format!("{}\n{}", status_text, long_text.join("\n").trim_end());
+        }
+        if status_text.is_empty() {
+            status_text = match code {
+                Some(code) => format!("Exited with code: {}", code),
+                None => "Terminated by signal".to_string(),
+            };
+        }
+
+        // An UNKNOWN state (or an exit code we don't know about) means the plugin couldn't do its job
+        match (code, &exit_code_value) {
+            (Some(_), None) | (_, Some(ExitCodeValue::Keyword(ExitCodeKeyword::Unknown))) => {
+                return (successes, ResultOutput::Unknown(status_text));
+            }
+            _ => {}
+        }
+
+        let mut results: Vec<TestResult> = vec![TestResult {
+            name: "status".to_string(),
+            success: successes,
+            result: json!(status_text),
+            status: None,
+        }];
+        results.extend(
+            Tester::split_perfdata(&perfdata)
+                .iter()
+                .filter_map(|item| Tester::parse_perfdata_item(item)),
+        );
+
+        (successes, ResultOutput::Result(results))
+    }
+
+    /// Splits a perfdata string into its items, respecting single quoted labels containing spaces.
+    fn split_perfdata(perfdata: &str) -> Vec<String> {
+        let mut items: Vec<String> = vec![];
+        let mut current = String::new();
+        let mut in_quotes = false;
+        for c in perfdata.chars() {
+            match c {
+                '\'' => {
+                    in_quotes = !in_quotes;
+                    current.push(c);
+                }
+                c if c.is_whitespace() && !in_quotes => {
+                    if !current.is_empty() {
+                        items.push(std::mem::take(&mut current));
+                    }
+                }
+                c => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            items.push(current);
+        }
+        items
+    }
+
+    /// Parses a single perfdata item, `'label'=value[UOM];[warn];[crit];[min];[max]`, into a `TestResult`.
+    fn parse_perfdata_item(item: &str) -> Option<TestResult> {
+        let (label, data) = item.rsplit_once('=')?;
+        let label = label.trim_matches('\'').replace("''", "'");
+        let mut fields = data.split(';');
+
+        let raw_value = fields.next().unwrap_or("");
+        let number_len = raw_value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E'))
+            .unwrap_or(raw_value.len());
+        let (number, uom) = raw_value.split_at(number_len);
+        let value: Option<f64> = number.parse().ok();
+        let uom = if value.is_some() { uom } else { "" };
+
+        let warn = fields.next().unwrap_or("");
+        let crit = fields.next().unwrap_or("");
+        let min = fields.next().and_then(|v| v.parse::<f64>().ok());
+        let max = fields.next().and_then(|v| v.parse::<f64>().ok());
+
+        let (success, status) = match value {
+            // "U" means the plugin couldn't determine the value
+            None => (0.0, Some(Status::Unknown)),
+            Some(v) if NagiosRange::parse(crit).is_some_and(|r| r.alerts(v)) => (0.0, None),
+            Some(v) if NagiosRange::parse(warn).is_some_and(|r| r.alerts(v)) => (0.5, None),
+            Some(_) => (1.0, None),
+        };
+
+        let to_value = |s: &str| if s.is_empty() { Value::Null } else { json!(s) };
+        Some(TestResult {
+            name: label,
+            success,
+            result: json!({
+                "value": value,
+                "uom": uom,
+                "warn": to_value(warn),
+                "crit": to_value(crit),
+                "min": min,
+                "max": max,
+            }),
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nagios_service() -> Service {
+        Service::for_tests(&json!({"name": "disk", "command": "check_disk", "format": "nagios"}))
+    }
+
+    #[test]
+    fn parses_threshold_ranges() {
+        // Alerts outside of 0 to 10
+        let range = NagiosRange::parse("10").unwrap();
+        assert!(range.alerts(-1.0));
+        assert!(!range.alerts(0.0));
+        assert!(!range.alerts(10.0));
+        assert!(range.alerts(10.5));
+
+        // Alerts below 10
+        let range = NagiosRange::parse("10:").unwrap();
+        assert!(range.alerts(9.9));
+        assert!(!range.alerts(10.0));
+        assert!(!range.alerts(1e12));
+
+        // Alerts above 10, however low it goes
+        let range = NagiosRange::parse("~:10").unwrap();
+        assert!(!range.alerts(-1e12));
+        assert!(range.alerts(11.0));
+
+        let range = NagiosRange::parse("10:20").unwrap();
+        assert!(range.alerts(9.0));
+        assert!(!range.alerts(15.0));
+        assert!(range.alerts(21.0));
+    }
+
+    #[test]
+    fn inverts_ranges_starting_with_an_at() {
+        let range = NagiosRange::parse("@10:20").unwrap();
+        assert!(range.alerts(10.0));
+        assert!(range.alerts(15.0));
+        assert!(range.alerts(20.0));
+        assert!(!range.alerts(9.0));
+        assert!(!range.alerts(21.0));
+
+        let range = NagiosRange::parse("@~:0").unwrap();
+        assert!(range.alerts(-5.0));
+        assert!(!range.alerts(0.5));
+    }
+
+    #[test]
+    fn rejects_malformed_ranges() {
+        assert!(NagiosRange::parse("").is_none());
+        assert!(NagiosRange::parse("  ").is_none());
+        assert!(NagiosRange::parse("@").is_none());
+        assert!(NagiosRange::parse("ten").is_none());
+        assert!(NagiosRange::parse("10:x").is_none());
+        assert!(NagiosRange::parse("~").is_none());
+    }
+
+    #[test]
+    fn parses_perfdata_items_against_their_thresholds() {
+        let item = Tester::parse_perfdata_item("time=0.5s;1;2;0;10").unwrap();
+        assert_eq!(item.name, "time");
+        assert_eq!(item.success, 1.0);
+        assert_eq!(
+            item.result,
+            json!({"value": 0.5, "uom": "s", "warn": "1", "crit": "2", "min": 0.0, "max": 10.0})
+        );
+
+        assert_eq!(Tester::parse_perfdata_item("load=1.5;1;2").unwrap().success, 0.5);
+        assert_eq!(Tester::parse_perfdata_item("load=3;1;2").unwrap().success, 0.0);
+        assert_eq!(Tester::parse_perfdata_item("free=5%;10:;5:").unwrap().success, 0.5);
+    }
+
+    #[test]
+    fn splits_perfdata_with_quoted_labels() {
+        let items = Tester::split_perfdata("'disk usage'=80%;90;95  'it''s'=1 time=2s");
+        assert_eq!(items, ["'disk usage'=80%;90;95", "'it''s'=1", "time=2s"]);
+        assert_eq!(Tester::parse_perfdata_item(&items[0]).unwrap().name, "disk usage");
+        assert_eq!(Tester::parse_perfdata_item(&items[1]).unwrap().name, "it's");
+    }
+
+    #[test]
+    fn handles_malformed_perfdata() {
+        assert!(Tester::parse_perfdata_item("garbage").is_none());
+
+        // A value the plugin couldn't determine
+        let item = Tester::parse_perfdata_item("time=U;1;2").unwrap();
+        assert_eq!(item.success, 0.0);
+        assert_eq!(item.status, Some(Status::Unknown));
+        assert_eq!(item.result["value"], Value::Null);
+
+        // Thresholds that can't be parsed don't alert
+        let item = Tester::parse_perfdata_item("time=5;abc;~").unwrap();
+        assert_eq!(item.success, 1.0);
+        assert_eq!(item.result["warn"], json!("abc"));
+    }
+
+    #[test]
+    fn parses_text_long_text_and_perfdata_over_multiple_lines() {
+        let output = "DISK WARNING - 80% used | root=80%;75;90\nchecked /\nchecked /home | home=10%;75;90\nvar=95%;75;90\n";
+        let (successes, ResultOutput::Result(results)) = Tester::format_nagios(output, Some(1), &nagios_service())
+        else {
+            panic!("expected test results");
+        };
+        assert_eq!(successes, 0.5);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["status", "root", "home", "var"]);
+        assert_eq!(results[0].result, json!("DISK WARNING - 80% used\nchecked /\nchecked /home"));
+        assert_eq!(results[1].success, 0.5);
+        assert_eq!(results[2].success, 1.0);
+        assert_eq!(results[3].success, 0.0);
+    }
+
+    #[test]
+    fn describes_empty_output_by_the_exit_code() {
+        let (successes, ResultOutput::Result(results)) = Tester::format_nagios("", Some(2), &nagios_service()) else {
+            panic!("expected test results");
+        };
+        assert_eq!(successes, 0.0);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].result, json!("Exited with code: 2"));
+
+        let (_, ResultOutput::Result(results)) = Tester::format_nagios("", None, &nagios_service()) else {
+            panic!("expected test results");
+        };
+        assert_eq!(results[0].result, json!("Terminated by signal"));
+    }
+
+    #[test]
+    fn unknown_and_unexpected_exit_codes_are_unknown() {
+        let (successes, result) = Tester::format_nagios("UNKNOWN - no such disk | x=1", Some(3), &nagios_service());
+        assert_eq!(successes, 0.0);
+        assert!(matches!(result, ResultOutput::Unknown(text) if text == "UNKNOWN - no such disk"));
+
+        let (_, result) = Tester::format_nagios("Segmentation fault", Some(139), &nagios_service());
+        assert!(matches!(result, ResultOutput::Unknown(text) if text == "Segmentation fault"));
+    }
+}
@@ -0,0 +1,344 @@
+use serde_json::{json, Value};
+
+use crate::settings::{ResultOutput, TestResult};
+use crate::tester::Tester;
+use crate::utils::status::Status;
+
+/// A single `ok`/`not ok` line from TAP output
+struct TapLine {
+    ok: bool,
+    number: Option<u64>,
+    description: String,
+    directive: Option<(String, String)>,
+}
+
+impl TapLine {
+    fn parse(line: &str) -> Option<TapLine> {
+        let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+            (false, rest)
+        } else if let Some(rest) = line.strip_prefix("ok") {
+            (true, rest)
+        } else {
+            return None;
+        };
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        // Directives are separated by an unescaped #
+        let (rest, directive) = match Tester::find_tap_directive(rest) {
+            Some(i) => {
+                let directive = rest[i + 1..].trim();
+                let (keyword, reason) = directive
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((directive, ""));
+                let keyword = keyword.to_uppercase();
+                if keyword.starts_with("SKIP") || keyword == "TODO" {
+                    let keyword = if keyword == "TODO" { "todo" } else { "skip" };
+                    (&rest[..i], Some((keyword.to_string(), reason.trim().to_string())))
+                } else {
+                    (rest, None)
+                }
+            }
+            None => (rest, None),
+        };
+
+        let rest = rest.trim();
+        let number_len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let number = rest[..number_len].parse::<u64>().ok();
+        let description = rest[number_len..]
+            .trim_start()
+            .trim_start_matches('-')
+            .trim()
+            .replace("\\#", "#");
+
+        Some(TapLine {
+            ok,
+            number,
+            description,
+            directive,
+        })
+    }
+}
+
+impl Tester {
+    /// Checks whether the output looks like TAP (Test Anything Protocol).
+    ///
+    /// The first non-empty line has to be a `TAP version` line or a plan (`1..N`). It can also be a test line
+    /// (`ok`/`not ok`) when the plan comes at the end, otherwise plain text starting with "ok" would be taken for TAP.
+    pub(crate) fn is_tap(value: &str) -> bool {
+        let mut lines = value.lines().map(str::trim_end).filter(|line| !line.is_empty());
+        match lines.next() {
+            Some(line) if line.starts_with("TAP version ") || Tester::parse_tap_plan(line).is_some() => true,
+            Some(line) if TapLine::parse(line).is_some() => {
+                lines.any(|line| Tester::parse_tap_plan(line).is_some())
+            }
+            _ => false,
+        }
+    }
+
+    /// Formats TAP (Test Anything Protocol) output into a `ResultOutput`.
+    ///
+    /// Every `ok`/`not ok` line becomes a `TestResult`, with the YAML diagnostics block (or `#` comments)
+    /// following it as the `result`. Tests with a `# SKIP` directive are marked as skipped, and failing
+    /// tests with a `# TODO` directive don't count as failures. A `Bail out!` or a plan that doesn't match
+    /// the amount of tests adds a failing test. A plan skipping everything (`1..0 # SKIP reason`) gives a single skipped test.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A string that represents the TAP output to be formatted.
+    ///
+    /// # Returns
+    ///
+    /// A `ResultOutput` that represents the formatted result.
+    pub(crate) fn format_tap(value: &str) -> ResultOutput {
+        let mut results: Vec<TestResult> = vec![];
+        let mut planned: Option<u64> = None;
+        let mut skip_all: Option<String> = None;
+        let mut diagnostics: Vec<String> = vec![];
+        let mut yaml: Option<Vec<String>> = None;
+        let mut in_yaml = false;
+        let mut current: Option<TapLine> = None;
+
+        fn finish(results: &mut Vec<TestResult>, line: TapLine, yaml: Option<Vec<String>>, diagnostics: Vec<String>) {
+            let name = if !line.description.is_empty() {
+                line.description
+            } else {
+                format!("test {}", line.number.unwrap_or(results.len() as u64 + 1))
+            };
+            let (success, status) = match line.directive.as_ref().map(|(k, _)| k.as_str()) {
+                Some("skip") => (1.0, Some(Status::Skipped)),
+                // A failing TODO test is expected to fail
+                Some("todo") => (1.0, None),
+                _ => (line.ok as i32 as f64, None),
+            };
+            let result = if let Some(yaml) = yaml {
+                json!(yaml.join("\n"))
+            } else if !diagnostics.is_empty() {
+                json!(diagnostics.join("\n"))
+            } else if let Some((_, reason)) = line.directive.filter(|(_, r)| !r.is_empty()) {
+                json!(reason)
+            } else {
+                Value::Null
+            };
+            results.push(TestResult {
+                name,
+                success,
+                result,
+                status,
+            });
+        }
+
+        for line in value.lines() {
+            let trimmed = line.trim();
+
+            // YAML diagnostics block belonging to the previous test line
+            if in_yaml {
+                if trimmed == "..." {
+                    in_yaml = false;
+                } else if let Some(yaml) = yaml.as_mut() {
+                    yaml.push(line.trim_start_matches("  ").to_string());
+                }
+                continue;
+            }
+            if trimmed == "---" && line.starts_with(char::is_whitespace) && current.is_some() && yaml.is_none() {
+                in_yaml = true;
+                yaml = Some(vec![]);
+                continue;
+            }
+
+            // Indented lines are subtests, those are summed up by their parent test line
+            if line.starts_with(char::is_whitespace) {
+                continue;
+            }
+
+            if let Some(tap_line) = TapLine::parse(trimmed) {
+                if let Some(previous) = current.take() {
+                    finish(&mut results, previous, yaml.take(), std::mem::take(&mut diagnostics));
+                }
+                current = Some(tap_line);
+            } else if let Some(plan) = Tester::parse_tap_plan(trimmed) {
+                planned = Some(plan);
+                if plan == 0 {
+                    skip_all = Some(match Tester::find_tap_directive(trimmed) {
+                        Some(i) => trimmed[i + 1..].trim().trim_start_matches("SKIP").trim().to_string(),
+                        None => String::new(),
+                    });
+                }
+            } else if let Some(reason) = trimmed.strip_prefix("Bail out!") {
+                if let Some(previous) = current.take() {
+                    finish(&mut results, previous, yaml.take(), std::mem::take(&mut diagnostics));
+                }
+                results.push(TestResult {
+                    name: "Bail out!".to_string(),
+                    success: 0.0,
+                    result: json!(reason.trim()),
+                    status: None,
+                });
+                planned = None;
+                break;
+            } else if let (Some(comment), Some(_)) = (trimmed.strip_prefix('#'), &current) {
+                diagnostics.push(comment.trim().to_string());
+            }
+        }
+        if let Some(previous) = current.take() {
+            finish(&mut results, previous, yaml.take(), diagnostics);
+        }
+
+        if let (Some(reason), true) = (skip_all, results.is_empty()) {
+            return ResultOutput::Result(vec![TestResult {
+                name: "plan".to_string(),
+                success: 1.0,
+                result: if reason.is_empty() { Value::Null } else { json!(reason) },
+                status: Some(Status::Skipped),
+            }]);
+        }
+        if let Some(planned) = planned {
+            let ran = results.len() as u64;
+            if planned != ran {
+                results.push(TestResult {
+                    name: "plan".to_string(),
+                    success: 0.0,
+                    result: json!(format!("Planned {} tests but ran {}", planned, ran)),
+                    status: None,
+                });
+            }
+        }
+
+        if !results.is_empty() {
+            ResultOutput::Result(results)
+        } else {
+            ResultOutput::Unknown("No tests found in the TAP output".to_string())
+        }
+    }
+
+    /// Makes sure a run whose TAP output only has passing tests still fails when the command didn't exit with 0,
+    /// like TAP harnesses treat it. A non-zero exit code is expected when a test failed, so it's ignored then.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of `format_tap`.
+    /// * `code` - The exit code of the command, `None` if it was terminated by a signal.
+    pub(crate) fn check_tap_exit_code(result: ResultOutput, code: Option<i32>) -> ResultOutput {
+        let ResultOutput::Result(mut results) = result else {
+            return result;
+        };
+        if code != Some(0) && results.iter().all(|r| r.success >= 1.0) {
+            let description = match code {
+                Some(code) => format!("Exited with non-zero code {} while every test passed", code),
+                None => "Terminated by a signal, most likely the timeout".to_string(),
+            };
+            results.push(TestResult {
+                name: "exit".to_string(),
+                success: 0.0,
+                result: json!(description),
+                status: None,
+            });
+        }
+        ResultOutput::Result(results)
+    }
+
+    /// Parses a TAP plan line, e.g. `1..4` or `1..0 # SKIP no database`, returning the amount of planned tests.
+    fn parse_tap_plan(line: &str) -> Option<u64> {
+        let plan = line.split('#').next()?.trim();
+        let (start, end) = plan.split_once("..")?;
+        if start != "1" && start != "0" {
+            return None;
+        }
+        end.parse::<u64>().ok()
+    }
+
+    /// Finds the position of the `#` starting a directive, ignoring escaped `\#`.
+    fn find_tap_directive(value: &str) -> Option<usize> {
+        let mut last: Option<char> = None;
+        for (i, c) in value.char_indices() {
+            if c == '#' && last != Some('\\') {
+                return Some(i);
+            }
+            last = Some(c);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_passing_and_failing_lines() {
+        let line = TapLine::parse("ok 1 - postgres is up").unwrap();
+        assert!(line.ok);
+        assert_eq!(line.number, Some(1));
+        assert_eq!(line.description, "postgres is up");
+        assert!(line.directive.is_none());
+
+        let line = TapLine::parse("not ok 2 web is up").unwrap();
+        assert!(!line.ok);
+        assert_eq!(line.number, Some(2));
+        assert_eq!(line.description, "web is up");
+    }
+
+    #[test]
+    fn parses_lines_without_number_or_description() {
+        let line = TapLine::parse("ok").unwrap();
+        assert!(line.ok);
+        assert_eq!(line.number, None);
+        assert_eq!(line.description, "");
+
+        let line = TapLine::parse("not ok - no number").unwrap();
+        assert_eq!(line.number, None);
+        assert_eq!(line.description, "no number");
+    }
+
+    #[test]
+    fn parses_directives() {
+        let line = TapLine::parse("ok 3 - redis is up # SKIP no redis on this host").unwrap();
+        assert_eq!(line.description, "redis is up");
+        assert_eq!(line.directive, Some(("skip".to_string(), "no redis on this host".to_string())));
+
+        let line = TapLine::parse("not ok 4 # todo not implemented").unwrap();
+        assert_eq!(line.directive, Some(("todo".to_string(), "not implemented".to_string())));
+
+        let line = TapLine::parse("ok 5 # skipped").unwrap();
+        assert_eq!(line.directive, Some(("skip".to_string(), String::new())));
+    }
+
+    #[test]
+    fn keeps_escaped_and_unknown_hashes_in_the_description() {
+        let line = TapLine::parse("ok 1 - issue \\#12 is fixed").unwrap();
+        assert_eq!(line.description, "issue #12 is fixed");
+        assert!(line.directive.is_none());
+
+        let line = TapLine::parse("ok 2 - counts # of users").unwrap();
+        assert_eq!(line.description, "counts # of users");
+        assert!(line.directive.is_none());
+    }
+
+    #[test]
+    fn rejects_lines_that_are_not_test_lines() {
+        assert!(TapLine::parse("okay then").is_none());
+        assert!(TapLine::parse("not okay").is_none());
+        assert!(TapLine::parse("1..4").is_none());
+        assert!(TapLine::parse("# comment").is_none());
+    }
+
+    #[test]
+    fn only_detects_tap_with_a_version_or_plan() {
+        assert!(Tester::is_tap("TAP version 13\nok 1\n"));
+        assert!(Tester::is_tap("1..1\nok 1\n"));
+        assert!(Tester::is_tap("ok 1\nok 2\n1..2\n"));
+        assert!(!Tester::is_tap("ok everything is fine\n"));
+        assert!(!Tester::is_tap("not ok, the disk is full\n"));
+    }
+
+    #[test]
+    fn skips_everything_with_a_skip_all_plan() {
+        let ResultOutput::Result(results) = Tester::format_tap("1..0 # SKIP no database\n") else {
+            panic!("expected test results");
+        };
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, Some(Status::Skipped));
+        assert_eq!(results[0].result, json!("no database"));
+    }
+}
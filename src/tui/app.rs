@@ -0,0 +1,192 @@
+use chrono::prelude::*;
+use std::collections::HashSet;
+
+use crate::service::Service;
+use crate::settings::{ResultOutput, TestResult};
+use crate::utils::status::Status;
+
+/// The column the services are sorted by
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SortKey {
+    Name,
+    Status,
+    Successes,
+    LastRun,
+    NextRun,
+}
+
+impl SortKey {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SortKey::Name => "name",
+            SortKey::Status => "status",
+            SortKey::Successes => "successes",
+            SortKey::LastRun => "last run",
+            SortKey::NextRun => "next run",
+        }
+    }
+
+    /// The column to sort by after this one
+    pub fn next(&self) -> SortKey {
+        match *self {
+            SortKey::Name => SortKey::Status,
+            SortKey::Status => SortKey::Successes,
+            SortKey::Successes => SortKey::LastRun,
+            SortKey::LastRun => SortKey::NextRun,
+            SortKey::NextRun => SortKey::Name,
+        }
+    }
+}
+
+/// A row of the table, either a service or one of the test results of an expanded service
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Row {
+    /// The index of the service
+    Service(usize),
+    /// The index of the service and of its test result
+    Test(usize, usize),
+}
+
+/// The raw output of a service, shown on top of the table
+pub struct RawView {
+    pub name: String,
+    pub text: String,
+    pub scroll: u16,
+}
+
+/// The state of the dashboard
+pub struct App {
+    /// The services, in the order they are shown
+    pub services: Vec<Service>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    /// The index of the selected row
+    pub selected: usize,
+    /// The names of the services showing their test results
+    pub expanded: HashSet<String>,
+    /// The reply to the last action, or the last error
+    pub message: Option<String>,
+    pub raw: Option<RawView>,
+    /// When the services were last fetched successfully
+    pub last_refresh: Option<DateTime<Utc>>,
+}
+
+impl App {
+    pub fn new() -> Self {
+        App {
+            services: vec![],
+            sort: SortKey::Name,
+            reverse: false,
+            selected: 0,
+            expanded: HashSet::new(),
+            message: None,
+            raw: None,
+            last_refresh: None,
+        }
+    }
+
+    /// Replaces the services with freshly fetched ones, keeping the same row selected
+    pub fn update(&mut self, services: Vec<Service>) {
+        let selected = self.selected_row();
+        self.services = services;
+        self.last_refresh = Some(Utc::now());
+        self.sort_services();
+        self.select(selected);
+    }
+
+    /// Sorts the services by the sort key, keeping the same row selected
+    pub fn sort_services(&mut self) {
+        let selected = self.selected_row();
+        let sort = self.sort;
+        self.services.sort_by(|a, b| {
+            let ordering = match sort {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Status => severity(a.status).cmp(&severity(b.status)),
+                SortKey::Successes => a.successes.total_cmp(&b.successes),
+                SortKey::LastRun => a.last_run.cmp(&b.last_run),
+                SortKey::NextRun => a.next_run.cmp(&b.next_run),
+            };
+            ordering.then_with(|| a.name.cmp(&b.name))
+        });
+        if self.reverse {
+            self.services.reverse();
+        }
+        self.select(selected);
+    }
+
+    /// The rows of the table, the test results of expanded services right below them
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        for (i, service) in self.services.iter().enumerate() {
+            rows.push(Row::Service(i));
+            if self.expanded.contains(&service.name) {
+                rows.extend((0..test_results(service).len()).map(|j| Row::Test(i, j)));
+            }
+        }
+        rows
+    }
+
+    /// The service of the selected row, also when a test result of it is selected
+    pub fn selected_service(&self) -> Option<&Service> {
+        match self.rows().get(self.selected)? {
+            Row::Service(i) | Row::Test(i, _) => self.services.get(*i),
+        }
+    }
+
+    /// Moves the selection up or down
+    pub fn move_selection(&mut self, offset: isize) {
+        let last = self.rows().len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    /// Shows or hides the test results of the selected service
+    pub fn toggle_expanded(&mut self) {
+        let Some(name) = self.selected_service().map(|s| s.name.clone()) else {
+            return;
+        };
+        if !self.expanded.remove(&name) {
+            self.expanded.insert(name.clone());
+        }
+        self.select(Some((name, None)));
+    }
+
+    /// The name of the service and the index of the test result of the selected row
+    fn selected_row(&self) -> Option<(String, Option<usize>)> {
+        match self.rows().get(self.selected)? {
+            Row::Service(i) => Some((self.services.get(*i)?.name.clone(), None)),
+            Row::Test(i, j) => Some((self.services.get(*i)?.name.clone(), Some(*j))),
+        }
+    }
+
+    /// Selects the row of a service or one of its test results, if it's still there
+    fn select(&mut self, row: Option<(String, Option<usize>)>) {
+        let rows = self.rows();
+        let found = row.and_then(|(name, test)| {
+            let i = self.services.iter().position(|s| s.name == name)?;
+            // Falls back to the service when the test result is gone
+            test.and_then(|j| rows.iter().position(|r| *r == Row::Test(i, j)))
+                .or_else(|| rows.iter().position(|r| *r == Row::Service(i)))
+        });
+        self.selected = found.unwrap_or(self.selected).min(rows.len().saturating_sub(1));
+    }
+}
+
+/// The test results of a service, empty if its result isn't a list of tests
+pub fn test_results(service: &Service) -> &[TestResult] {
+    match &service.result {
+        ResultOutput::Result(results) => results,
+        _ => &[],
+    }
+}
+
+/// Orders statuses by how much attention they need, the worst first
+fn severity(status: Status) -> u8 {
+    match status {
+        Status::Critical => 0,
+        Status::Unknown => 1,
+        Status::Warning => 2,
+        Status::Pending => 3,
+        Status::Skipped => 4,
+        Status::Ok => 5,
+    }
+}
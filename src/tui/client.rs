@@ -0,0 +1,100 @@
+use zmq::{Context, Socket, DEALER, POLLIN};
+
+use crate::zmq_handler::curve::ClientKeys;
+
+/// How long to wait for a reply from the daemon, in milliseconds
+const REPLY_TIMEOUT_MS: i64 = 3000;
+
+/// How to reach the control socket of the daemon
+pub struct ClientOptions {
+    /// The ZMQ endpoint of the daemon, e.g. `tcp://127.0.0.1:5747`
+    pub endpoint: String,
+    /// A shared token identifying the client, sent along with every command
+    pub token: Option<String>,
+    /// The file with the z85 encoded CURVE public key of the daemon
+    pub server_key: Option<String>,
+    /// The prefix of the `.key` and `.pub` files of the client, from `status keygen`
+    pub client_key: Option<String>,
+}
+
+/// A DEALER socket sending commands to the daemon, the same way `daemon/main.py` does
+pub struct Client {
+    context: Context,
+    socket: Socket,
+    endpoint: String,
+    token: Option<String>,
+    curve: Option<ClientKeys>,
+}
+
+impl Client {
+    /// Creates a new `Client` connected to the daemon.
+    ///
+    /// # Returns
+    ///
+    /// The client, or why the CURVE keys couldn't be read or the socket couldn't connect.
+    pub fn new(options: ClientOptions) -> Result<Client, String> {
+        let curve = match (&options.server_key, &options.client_key) {
+            (Some(server_key), Some(client_key)) => Some(ClientKeys::load(server_key, client_key)?),
+            (None, None) => None,
+            _ => return Err("CURVE needs both --server-key and --client-key".to_string()),
+        };
+        let context = Context::new();
+        let socket = connect(&context, &options.endpoint, curve.as_ref())?;
+        Ok(Client {
+            context,
+            socket,
+            endpoint: options.endpoint,
+            token: options.token,
+            curve,
+        })
+    }
+
+    /// Sends a command to the daemon and waits for the reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command, as it would be given to `daemon/main.py`, e.g. `service --short`.
+    ///
+    /// # Returns
+    ///
+    /// The reply of the daemon, or why there was none.
+    pub fn request(&mut self, command: &str) -> Result<String, String> {
+        let content = match &self.token {
+            Some(token) => serde_json::json!({"command": command, "token": token}).to_string(),
+            None => command.to_string(),
+        };
+        self.socket
+            .send(content.as_bytes(), 0)
+            .map_err(|e| format!("Unable to send to {}: {}", self.endpoint, e))?;
+
+        let ready = self
+            .socket
+            .poll(POLLIN, REPLY_TIMEOUT_MS)
+            .map_err(|e| format!("Unable to poll {}: {}", self.endpoint, e))?;
+        if ready == 0 {
+            // A late reply would otherwise be taken for the reply of the next command
+            self.socket = connect(&self.context, &self.endpoint, self.curve.as_ref())?;
+            return Err(format!("No reply from {}", self.endpoint));
+        }
+        let reply = self
+            .socket
+            .recv_bytes(0)
+            .map_err(|e| format!("Unable to receive from {}: {}", self.endpoint, e))?;
+        Ok(String::from_utf8_lossy(&reply).to_string())
+    }
+}
+
+/// Creates a DEALER socket connected to the endpoint, using CURVE if there are keys
+fn connect(context: &Context, endpoint: &str, curve: Option<&ClientKeys>) -> Result<Socket, String> {
+    let socket = context
+        .socket(DEALER)
+        .map_err(|e| format!("Unable to create socket: {}", e))?;
+    socket.set_linger(0).map_err(|e| e.to_string())?;
+    if let Some(curve) = curve {
+        curve.apply(&socket).map_err(|e| e.to_string())?;
+    }
+    socket
+        .connect(endpoint)
+        .map_err(|e| format!("Unable to connect to {}: {}", endpoint, e))?;
+    Ok(socket)
+}
@@ -0,0 +1,172 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::DefaultTerminal;
+use serde_json::Value;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::service::Service;
+use crate::tui::app::{App, RawView};
+use crate::tui::client::Client;
+
+pub use crate::tui::client::ClientOptions;
+
+mod app;
+mod client;
+mod ui;
+
+/// Runs the terminal dashboard until the user quits.
+///
+/// The services are fetched with the `service` command every `refresh` seconds, and after every action.
+///
+/// # Arguments
+///
+/// * `options` - How to reach the control socket of the daemon.
+/// * `refresh` - How often to fetch the services, in seconds.
+pub fn run(options: ClientOptions, refresh: u64) -> io::Result<()> {
+    let endpoint = options.endpoint.clone();
+    let mut client = Client::new(options).map_err(io::Error::other)?;
+    let mut app = App::new();
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut client, &mut app, &endpoint, Duration::from_secs(refresh.max(1)));
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &mut Client,
+    app: &mut App,
+    endpoint: &str,
+    refresh: Duration,
+) -> io::Result<()> {
+    let mut next_refresh = Instant::now();
+    loop {
+        if Instant::now() >= next_refresh {
+            fetch_services(client, app);
+            next_refresh = Instant::now() + refresh;
+        }
+        terminal.draw(|frame| ui::draw(frame, app, endpoint))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if app.raw.is_some() {
+            raw_view_key(app, key);
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::PageUp => app.move_selection(-10),
+            KeyCode::PageDown => app.move_selection(10),
+            KeyCode::Enter | KeyCode::Char(' ') => app.toggle_expanded(),
+            KeyCode::Char('s') => {
+                app.sort = app.sort.next();
+                app.sort_services();
+                app.message = Some(format!("Sorted by {}", app.sort.as_str()));
+            }
+            KeyCode::Char('S') => {
+                app.reverse = !app.reverse;
+                app.sort_services();
+            }
+            KeyCode::Char('r') => {
+                if let Some(name) = app.selected_service().map(|s| s.name.clone()) {
+                    send_action(client, app, &format!("run {}", name));
+                    next_refresh = Instant::now();
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(service) = app.selected_service() {
+                    let action = if service.paused { "resume" } else { "pause" };
+                    let command = format!("{} {}", action, service.name);
+                    send_action(client, app, &command);
+                    next_refresh = Instant::now();
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(name) = app.selected_service().map(|s| s.name.clone()) {
+                    show_raw_output(client, app, &name);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Handles the keys while the raw output is shown
+fn raw_view_key(app: &mut App, key: KeyEvent) {
+    let Some(raw) = &mut app.raw else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => app.raw = None,
+        KeyCode::Up | KeyCode::Char('k') => raw.scroll = raw.scroll.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => raw.scroll = raw.scroll.saturating_add(1),
+        KeyCode::PageUp => raw.scroll = raw.scroll.saturating_sub(20),
+        KeyCode::PageDown => raw.scroll = raw.scroll.saturating_add(20),
+        KeyCode::Home => raw.scroll = 0,
+        _ => {}
+    }
+}
+
+/// Fetches the services, keeping the old ones on screen if that fails
+fn fetch_services(client: &mut Client, app: &mut App) {
+    let reply = match client.request("service") {
+        Ok(reply) => reply,
+        Err(e) => {
+            app.message = Some(e);
+            return;
+        }
+    };
+    match serde_json::from_str::<Vec<Service>>(&reply) {
+        Ok(services) => app.update(services),
+        // Not JSON, e.g. "Access denied: ..." or "No services found"
+        Err(_) => app.message = Some(reply),
+    }
+}
+
+/// Sends a command changing a service, showing the reply
+fn send_action(client: &mut Client, app: &mut App, command: &str) {
+    app.message = Some(client.request(command).unwrap_or_else(|e| e));
+}
+
+/// Fetches the raw stdout and stderr of the last run of a service, and shows them
+fn show_raw_output(client: &mut Client, app: &mut App, name: &str) {
+    let reply = match client.request(&format!("service {} --raw", name)) {
+        Ok(reply) => reply,
+        Err(e) => {
+            app.message = Some(e);
+            return;
+        }
+    };
+    let raw = match serde_json::from_str::<Value>(&reply) {
+        Ok(value) => value.get(0).and_then(|s| s.get("raw")).cloned().unwrap_or(Value::Null),
+        Err(_) => {
+            app.message = Some(reply);
+            return;
+        }
+    };
+    let text = if raw.is_null() {
+        "The service hasn't run yet".to_string()
+    } else {
+        let field = |key: &str| raw.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let exit_code = match raw.get("exit_code").and_then(|v| v.as_i64()) {
+            Some(code) => code.to_string(),
+            None => "none (killed)".to_string(),
+        };
+        format!("Exit code: {}\n\n── stdout ──\n{}\n── stderr ──\n{}", exit_code, field("stdout"), field("stderr"))
+    };
+    app.raw = Some(RawView {
+        name: name.to_string(),
+        text,
+        scroll: 0,
+    });
+}
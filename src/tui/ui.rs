@@ -0,0 +1,176 @@
+use chrono::prelude::*;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row as TableRow, Table, TableState, Wrap};
+use ratatui::Frame;
+use serde_json::Value;
+
+use crate::service::Service;
+use crate::settings::ResultOutput;
+use crate::tui::app::{test_results, App, Row, SortKey};
+use crate::utils::status::Status;
+
+const COLUMNS: [(&str, Option<SortKey>); 6] = [
+    ("Name", Some(SortKey::Name)),
+    ("Status", Some(SortKey::Status)),
+    ("Successes", Some(SortKey::Successes)),
+    ("Last run", Some(SortKey::LastRun)),
+    ("Next run", Some(SortKey::NextRun)),
+    ("Details", None),
+];
+
+const HELP: &str = "↑/↓ select  enter expand  r run  p pause/resume  o raw output  s sort  S reverse  q quit";
+
+/// Draws the dashboard
+pub fn draw(frame: &mut Frame, app: &App, endpoint: &str) {
+    let [table_area, help_area, message_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)]).areas(frame.area());
+
+    let header = TableRow::new(COLUMNS.iter().map(|(title, key)| {
+        let arrow = match key {
+            Some(key) if *key == app.sort && app.reverse => " ▲",
+            Some(key) if *key == app.sort => " ▼",
+            _ => "",
+        };
+        Cell::from(format!("{}{}", title, arrow))
+    }))
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let now = Utc::now();
+    let rows = app.rows().into_iter().map(|row| match row {
+        Row::Service(i) => service_row(&app.services[i], app.expanded.contains(&app.services[i].name), now),
+        Row::Test(i, j) => {
+            let test = &test_results(&app.services[i])[j];
+            let result = match &test.result {
+                Value::String(s) => s.lines().next().unwrap_or_default().to_string(),
+                other => other.to_string(),
+            };
+            TableRow::new(vec![
+                Cell::from(format!("  └ {}", test.name)),
+                status_cell(test.status),
+                Cell::from(format!("{:.2}", test.success)),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(result),
+            ])
+        }
+    });
+
+    let title = match app.last_refresh {
+        Some(refreshed) => format!(" {} · {} services · updated {} ", endpoint, app.services.len(), refreshed.with_timezone(&Local).format("%H:%M:%S")),
+        None => format!(" {} · connecting ", endpoint),
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(9),
+            Constraint::Length(11),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .block(Block::bordered().title(title))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, table_area, &mut state);
+
+    frame.render_widget(Paragraph::new(HELP).style(Style::default().fg(Color::DarkGray)), help_area);
+    if let Some(message) = &app.message {
+        frame.render_widget(Paragraph::new(message.lines().collect::<Vec<_>>().join(" · ")), message_area);
+    }
+
+    if let Some(raw) = &app.raw {
+        let area = centered(frame.area(), 90, 80);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(raw.text.as_str())
+                .block(Block::bordered().title(format!(" Raw output of {} · esc to close ", raw.name)))
+                .wrap(Wrap { trim: false })
+                .scroll((raw.scroll, 0)),
+            area,
+        );
+    }
+}
+
+/// The row of a service, `expanded` telling whether its test results are shown below it
+fn service_row(service: &Service, expanded: bool, now: DateTime<Utc>) -> TableRow<'static> {
+    let marker = match (test_results(service).is_empty(), expanded) {
+        (true, _) => "  ",
+        (false, true) => "▾ ",
+        (false, false) => "▸ ",
+    };
+    let successes = match service.status {
+        Status::Pending | Status::Skipped => "-".to_string(),
+        _ => format!("{:.2}", service.successes),
+    };
+    let last_run = match service.last_run {
+        Some(last_run) => format!("{} ago", age(now - last_run)),
+        None => "never".to_string(),
+    };
+    let next_run = match service.next_run {
+        _ if service.paused => "paused".to_string(),
+        Some(next_run) if next_run > now => format!("in {}", age(next_run - now)),
+        Some(_) => "now".to_string(),
+        None => "running".to_string(),
+    };
+    let mut details: Vec<String> = service.group.iter().cloned().collect();
+    details.extend(service.tags.iter().map(|tag| format!("#{}", tag)));
+    if let ResultOutput::Unknown(reason) = &service.result {
+        details.extend(reason.lines().next().map(String::from));
+    }
+
+    TableRow::new(vec![
+        Cell::from(format!("{}{}", marker, service.name)),
+        status_cell(Some(service.status)),
+        Cell::from(successes),
+        Cell::from(last_run),
+        Cell::from(next_run),
+        Cell::from(details.join(" ")),
+    ])
+}
+
+fn status_cell(status: Option<Status>) -> Cell<'static> {
+    let Some(status) = status else {
+        return Cell::from("");
+    };
+    let color = match status {
+        Status::Ok => Color::Green,
+        Status::Warning => Color::Yellow,
+        Status::Critical => Color::Red,
+        Status::Unknown => Color::Magenta,
+        Status::Pending | Status::Skipped => Color::DarkGray,
+    };
+    Cell::from(Line::from(Span::styled(status.as_str(), Style::default().fg(color))))
+}
+
+/// A short human readable duration, e.g. `42s`, `5m` or `3h`
+fn age(duration: chrono::TimeDelta) -> String {
+    let seconds = duration.num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+/// A rectangle in the middle of `area`, taking up a percentage of its width and height
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [_, middle, _] = Layout::vertical([
+        Constraint::Percentage((100 - height) / 2),
+        Constraint::Percentage(height),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    let [_, center, _] = Layout::horizontal([
+        Constraint::Percentage((100 - width) / 2),
+        Constraint::Percentage(width),
+        Constraint::Fill(1),
+    ])
+    .areas(middle);
+    center
+}
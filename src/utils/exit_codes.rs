@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Keywords that can be used instead of a success score in the `exit_codes` mapping
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitCodeKeyword {
+    Unknown,
+}
+
+/// What a specific exit code of a command means
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ExitCodeValue {
+    Success(f64),
+    Keyword(ExitCodeKeyword),
+}
+
+impl ExitCodeValue {
+    /// Parses a single value of the `exit_codes` mapping, either a number between 0.00 and 1.00 or `"unknown"`.
+    pub fn from_value(value: &Value) -> Option<ExitCodeValue> {
+        match value {
+            Value::Number(n) => n
+                .as_f64()
+                .filter(|f| (0.00..=1.00).contains(f))
+                .map(ExitCodeValue::Success),
+            Value::Bool(b) => Some(ExitCodeValue::Success(*b as i32 as f64)),
+            Value::String(s) if s == "unknown" => Some(ExitCodeValue::Keyword(ExitCodeKeyword::Unknown)),
+            _ => None,
+        }
+    }
+
+    pub fn to_successes(&self) -> f64 {
+        match self {
+            ExitCodeValue::Success(f) => *f,
+            ExitCodeValue::Keyword(ExitCodeKeyword::Unknown) => 0.0,
+        }
+    }
+}
+
+/// Parses the `exit_codes` object of a service, e.g. `{"1": 0.5, "3": "unknown"}`.
+/// Entries with an invalid exit code or value are skipped, those are reported by the settings validation.
+pub fn parse_exit_codes(value: &Value) -> HashMap<i32, ExitCodeValue> {
+    value
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| {
+                    let code = k.trim().parse::<i32>().ok()?;
+                    Some((code, ExitCodeValue::from_value(v)?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The exit code semantics used by Nagios plugins
+pub fn nagios_exit_codes() -> HashMap<i32, ExitCodeValue> {
+    HashMap::from([
+        (0, ExitCodeValue::Success(1.0)),
+        (1, ExitCodeValue::Success(0.5)),
+        (2, ExitCodeValue::Success(0.0)),
+        (3, ExitCodeValue::Keyword(ExitCodeKeyword::Unknown)),
+    ])
+}
@@ -0,0 +1,157 @@
+use std::env;
+use std::fs;
+
+/// Replaces the placeholders in a settings string.
+///
+/// * `${NAME}` - The environment variable `NAME`, failing if it isn't set.
+/// * `${NAME:-default}` - The environment variable `NAME`, or `default` if it isn't set or is empty.
+/// * `${file:/run/secrets/token}` - The content of a file, without the trailing newline.
+///
+/// `$${` is kept as a literal `${`.
+///
+/// # Arguments
+///
+/// * `value` - The string to interpolate.
+///
+/// # Returns
+///
+/// The interpolated string, or a message explaining what's wrong.
+pub fn interpolate(value: &str) -> Result<String, String> {
+    let mut interpolated = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        // Escaped as $${
+        if rest[..start].ends_with('$') {
+            interpolated.push_str(&rest[..start - 1]);
+            interpolated.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        interpolated.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("unclosed \"${{\" in \"{}\"", value));
+        };
+        let expression = &rest[start + 2..start + end];
+        rest = &rest[start + end + 1..];
+
+        if let Some(path) = expression.strip_prefix("file:") {
+            let content = fs::read_to_string(path.trim())
+                .map_err(|e| format!("unable to read secret file {}: {}", path.trim(), e))?;
+            interpolated.push_str(content.trim_end_matches(['\n', '\r']));
+            continue;
+        }
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid environment variable name \"{}\"", name));
+        }
+        match (env::var(name), default) {
+            (Ok(v), Some(default)) if v.is_empty() => interpolated.push_str(default),
+            (Ok(v), _) => interpolated.push_str(&v),
+            (Err(_), Some(default)) => interpolated.push_str(default),
+            (Err(_), None) => return Err(format!("environment variable {} is not set", name)),
+        }
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::service::Service;
+
+    // Set by cargo for the tests, so no environment variables have to be changed
+    const PACKAGE: &str = env!("CARGO_PKG_NAME");
+    const UNSET: &str = "STATUS_TEST_VARIABLE_THAT_IS_NOT_SET";
+
+    /// A secret file for a single test, removed once the test is done
+    struct SecretFile(std::path::PathBuf);
+
+    impl SecretFile {
+        fn new(name: &str, content: &str) -> SecretFile {
+            let path = env::temp_dir().join(format!("status-test-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            SecretFile(path)
+        }
+
+        fn path(&self) -> String {
+            self.0.to_string_lossy().to_string()
+        }
+    }
+
+    impl Drop for SecretFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn replaces_environment_variables() {
+        assert_eq!(interpolate("--app ${CARGO_PKG_NAME}").unwrap(), format!("--app {}", PACKAGE));
+        assert_eq!(interpolate(&format!("${{{}:-fallback}}", UNSET)).unwrap(), "fallback");
+        assert_eq!(interpolate("${CARGO_PKG_NAME:-fallback}").unwrap(), PACKAGE);
+        assert_eq!(interpolate("no placeholders").unwrap(), "no placeholders");
+    }
+
+    #[test]
+    fn fails_on_a_missing_variable() {
+        assert_eq!(
+            interpolate(&format!("Bearer ${{{}}}", UNSET)).unwrap_err(),
+            format!("environment variable {} is not set", UNSET)
+        );
+    }
+
+    #[test]
+    fn reads_secret_files_without_the_trailing_newline() {
+        let secret = SecretFile::new("token", "hunter2\r\n");
+        assert_eq!(interpolate(&format!("${{file:{}}}", secret.path())).unwrap(), "hunter2");
+        assert_eq!(interpolate(&format!("token=${{file: {} }};", secret.path())).unwrap(), "token=hunter2;");
+    }
+
+    #[test]
+    fn fails_on_a_missing_file() {
+        let error = interpolate("${file:/nonexistent/status/token}").unwrap_err();
+        assert!(error.starts_with("unable to read secret file /nonexistent/status/token:"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_malformed_placeholders() {
+        assert_eq!(interpolate("${CARGO_PKG_NAME").unwrap_err(), "unclosed \"${\" in \"${CARGO_PKG_NAME\"");
+        assert_eq!(interpolate("${}").unwrap_err(), "invalid environment variable name \"\"");
+        assert_eq!(interpolate("${API-TOKEN}").unwrap_err(), "invalid environment variable name \"API-TOKEN\"");
+    }
+
+    #[test]
+    fn keeps_escaped_placeholders() {
+        assert_eq!(interpolate("echo $${HOME} ${CARGO_PKG_NAME}").unwrap(), format!("echo ${{HOME}} {}", PACKAGE));
+    }
+
+    #[test]
+    fn redacts_interpolated_values() {
+        let secret = SecretFile::new("password", "hunter2\n");
+        let file_placeholder = format!("${{file:{}}}", secret.path());
+        let service = Service::for_tests(&json!({
+            "name": "db",
+            "command": "${CARGO_PKG_NAME}",
+            "args": ["--user", "admin", "--password", file_placeholder],
+            "env": {"APP": "${CARGO_PKG_NAME}", "PASSWORD": file_placeholder, "MODE": "plain"},
+        }));
+        // The test itself runs with the secrets filled in
+        assert_eq!(service.command, PACKAGE);
+        assert_eq!(service.args.as_ref().unwrap()[3], "hunter2");
+        assert_eq!(service.env["PASSWORD"], "hunter2");
+
+        // Clients only get to see how they were written
+        let redacted = service.redacted();
+        assert_eq!(redacted.command, "${CARGO_PKG_NAME}");
+        assert_eq!(redacted.args.unwrap(), ["--user", "admin", "--password", file_placeholder.as_str()]);
+        assert_eq!(redacted.env["APP"], "${CARGO_PKG_NAME}");
+        assert_eq!(redacted.env["PASSWORD"], file_placeholder);
+        assert_eq!(redacted.env["MODE"], "plain");
+    }
+}
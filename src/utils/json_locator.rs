@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Finds the line and column of every key and value in a JSON document, by path.
+///
+/// Paths look like `services[1].args[0]`, the root being an empty path.
+/// Object members point to their key, array elements to their value. Lines and columns start at 1.
+///
+/// This expects valid JSON (comments already replaced by whitespace), and stops at the first thing it doesn't understand.
+pub fn locate(json: &str) -> HashMap<String, (usize, usize)> {
+    let mut locator = Locator {
+        chars: json.chars().peekable(),
+        line: 1,
+        column: 1,
+        locations: HashMap::new(),
+    };
+    locator.value(String::new());
+    locator.locations
+}
+
+struct Locator<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    locations: HashMap<String, (usize, usize)>,
+}
+
+impl Locator<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn mark(&mut self, path: &str) {
+        self.locations
+            .entry(path.to_string())
+            .or_insert((self.line, self.column));
+    }
+
+    fn value(&mut self, path: String) -> Option<()> {
+        self.skip_whitespace();
+        self.mark(&path);
+        match *self.chars.peek()? {
+            '{' => {
+                self.next();
+                loop {
+                    self.skip_whitespace();
+                    if *self.chars.peek()? == '}' {
+                        self.next();
+                        return Some(());
+                    }
+                    let (line, column) = (self.line, self.column);
+                    let key = self.string()?;
+                    let child = if path.is_empty() { key } else { format!("{}.{}", path, key) };
+                    self.locations.entry(child.clone()).or_insert((line, column));
+                    self.skip_whitespace();
+                    if self.next()? != ':' {
+                        return None;
+                    }
+                    self.value(child)?;
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        '}' => return Some(()),
+                        _ => return None,
+                    }
+                }
+            }
+            '[' => {
+                self.next();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    if *self.chars.peek()? == ']' {
+                        self.next();
+                        return Some(());
+                    }
+                    self.value(format!("{}[{}]", path, index))?;
+                    index += 1;
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        ']' => return Some(()),
+                        _ => return None,
+                    }
+                }
+            }
+            '"' => self.string().map(|_| ()),
+            _ => {
+                // Numbers, booleans and null
+                while self
+                    .chars
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}'))
+                {
+                    self.next();
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.next()? != '"' {
+            return None;
+        }
+        let mut string = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Some(string),
+                '\\' => {
+                    let escaped = self.next()?;
+                    string.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        c => c,
+                    });
+                }
+                c => string.push(c),
+            }
+        }
+    }
+}
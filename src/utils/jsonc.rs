@@ -35,9 +35,7 @@ pub fn strip_jsonc_comments(jsonc_input: &str, preserve_locations: bool) -> Stri
                 }
             // Check for block comment end
             } else if !is_in_string && last_char == Some('*') && cur_char == '/' {
-                if block_comment_depth > 0 {
-                    block_comment_depth -= 1;
-                }
+                block_comment_depth = block_comment_depth.saturating_sub(1);
                 last_char = None;
                 if preserve_locations {
                     json_output.push_str("  ");
@@ -50,7 +48,7 @@ pub fn strip_jsonc_comments(jsonc_input: &str, preserve_locations: bool) -> Stri
                     }
                 } else {
                     if preserve_locations {
-                        json_output.push_str(" ");
+                        json_output.push(' ');
                     }
                 }
                 last_char = Some(cur_char);
@@ -1,3 +1,14 @@
 pub mod protocol;
 pub mod retry_strategy;
-pub mod jsonc;
\ No newline at end of file
+pub mod jsonc;
+pub mod ring_buffer;
+pub mod output_format;
+pub mod exit_codes;
+pub mod status;
+pub mod json_locator;
+pub mod settings_format;
+pub mod interpolation;
+pub mod templates;
+pub mod partial_success;
+pub mod time;
+pub mod html;
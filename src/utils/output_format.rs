@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Auto,
+    Json,
+    Plain,
+    ExitCode,
+    Jsonl,
+    Nagios,
+    Tap,
+    Junit,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            OutputFormat::Auto => "auto",
+            OutputFormat::Json => "json",
+            OutputFormat::Plain => "plain",
+            OutputFormat::ExitCode => "exit_code",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Nagios => "nagios",
+            OutputFormat::Tap => "tap",
+            OutputFormat::Junit => "junit",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(format: &str) -> Option<OutputFormat> {
+        match format {
+            "auto" => Some(OutputFormat::Auto),
+            "json" => Some(OutputFormat::Json),
+            "plain" => Some(OutputFormat::Plain),
+            "exit_code" => Some(OutputFormat::ExitCode),
+            "jsonl" => Some(OutputFormat::Jsonl),
+            "nagios" => Some(OutputFormat::Nagios),
+            "tap" => Some(OutputFormat::Tap),
+            "junit" => Some(OutputFormat::Junit),
+            _ => None,
+        }
+    }
+
+    /// Whether the format determines the outcome by itself, even when the command exits with a non-zero code.
+    /// Test runners producing TAP or JUnit reports and Nagios plugins all exit non-zero on failing tests,
+    /// and JSON Lines keep the results written before the command failed or timed out.
+    pub fn handles_exit_code(&self) -> bool {
+        matches!(self, OutputFormat::Nagios | OutputFormat::Tap | OutputFormat::Junit | OutputFormat::Jsonl)
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+
+/// How runs that only partly succeeded (e.g. a success score of 0.5) count towards the uptime in reports
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialSuccess {
+    /// Counts as the success score, 0.5 being half available
+    Weighted,
+    /// Counts as available, as long as anything succeeded
+    Up,
+    /// Counts as unavailable, only a full success is available
+    Down,
+}
+
+impl PartialSuccess {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PartialSuccess::Weighted => "weighted",
+            PartialSuccess::Up => "up",
+            PartialSuccess::Down => "down",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(partial_success: &str) -> Option<PartialSuccess> {
+        match partial_success {
+            "weighted" => Some(PartialSuccess::Weighted),
+            "up" => Some(PartialSuccess::Up),
+            "down" => Some(PartialSuccess::Down),
+            _ => None,
+        }
+    }
+
+    /// How available a run with this success score was, between 0.0 and 1.0
+    pub fn availability(&self, success: f64) -> f64 {
+        match *self {
+            PartialSuccess::Weighted => success.clamp(0.0, 1.0),
+            PartialSuccess::Up if success > 0.0 => 1.0,
+            PartialSuccess::Down if success >= 1.0 => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Display for PartialSuccess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
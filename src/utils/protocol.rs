@@ -19,6 +19,7 @@ impl Protocol {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(protocol: &str) -> Option<Protocol> {
         match protocol {
             "tcp" => Some(Protocol::Tcp),
@@ -22,6 +22,7 @@ impl RetryStrategy {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(retry_strategy: &str) -> Option<RetryStrategy> {
         match retry_strategy {
             "best" => Some(RetryStrategy::Best),
@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+
+/// A byte buffer that only keeps the last `capacity` bytes written to it.
+///
+/// Used when capturing the output of a test command, so that a runaway process
+/// printing gigabytes of data can't make the daemon run out of memory.
+pub struct RingBuffer {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    dropped: usize,
+}
+
+impl RingBuffer {
+    /// Creates a new `RingBuffer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum amount of bytes to keep. `0` means unlimited.
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            buffer: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// Appends bytes to the buffer, discarding the oldest bytes if the capacity is exceeded.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.capacity == 0 {
+            self.buffer.extend(bytes);
+            return;
+        }
+
+        // No need to copy bytes that would be discarded right away
+        let skip = bytes.len().saturating_sub(self.capacity);
+        self.dropped += skip;
+        self.buffer.extend(&bytes[skip..]);
+
+        let overflow = self.buffer.len().saturating_sub(self.capacity);
+        if overflow > 0 {
+            self.buffer.drain(..overflow);
+            self.dropped += overflow;
+        }
+    }
+
+    /// Converts the buffer into a (lossy) UTF-8 string.
+    ///
+    /// If any bytes were discarded, a truncation marker is put in front of the content.
+    pub fn into_string(self) -> String {
+        let buffer: Vec<u8> = self.buffer.into();
+        let content = String::from_utf8_lossy(&buffer).to_string();
+        if self.dropped > 0 {
+            format!("[... {} bytes truncated ...]\n{}", self.dropped, content)
+        } else {
+            content
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::utils::json_locator::locate;
+use crate::utils::jsonc::strip_jsonc_comments;
+
+/// The file formats the settings can be written in, detected by the file extension
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SettingsFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// The settings parsed into JSON, regardless of the file format
+pub struct ParsedSettings {
+    pub json: Value,
+    /// Line and column of every key and value by path, only available for JSON
+    pub locations: HashMap<String, (usize, usize)>,
+}
+
+impl SettingsFormat {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SettingsFormat::Json => "json",
+            SettingsFormat::Yaml => "yaml",
+            SettingsFormat::Toml => "toml",
+        }
+    }
+
+    /// Detects the format from the file extension, anything unknown is read as JSON(C).
+    pub fn from_path(path: &str) -> SettingsFormat {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match extension.as_deref() {
+            Some("yaml") | Some("yml") => SettingsFormat::Yaml,
+            Some("toml") => SettingsFormat::Toml,
+            _ => SettingsFormat::Json,
+        }
+    }
+
+    /// Parses the content of a settings file into JSON, so every format ends up with the same settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The content of the settings file.
+    ///
+    /// # Returns
+    ///
+    /// The parsed settings, or the line and column (if known) and message of the syntax error.
+    pub fn parse(&self, content: &str) -> Result<ParsedSettings, (Option<(usize, usize)>, String)> {
+        match self {
+            SettingsFormat::Json => {
+                // In case the person is using JSONC, just run it through this comment-stripper
+                let stripped_json = strip_jsonc_comments(content, true);
+                let json: Value = serde_json::from_str(&stripped_json)
+                    .map_err(|e| (Some((e.line(), e.column())), format!("Invalid JSON: {}", e)))?;
+                Ok(ParsedSettings {
+                    json,
+                    locations: locate(&stripped_json),
+                })
+            }
+            SettingsFormat::Yaml => {
+                let yaml: serde_norway::Value = serde_norway::from_str(content).map_err(|e| {
+                    let location = e.location().map(|l| (l.line(), l.column()));
+                    (location, format!("Invalid YAML: {}", e))
+                })?;
+                Ok(ParsedSettings {
+                    json: yaml_to_json(yaml),
+                    locations: HashMap::new(),
+                })
+            }
+            SettingsFormat::Toml => {
+                let toml: toml::Value = toml::from_str(content).map_err(|e| {
+                    let location = e.span().map(|span| line_column(content, span.start));
+                    (location, format!("Invalid TOML: {}", e.message()))
+                })?;
+                Ok(ParsedSettings {
+                    json: toml_to_json(toml),
+                    locations: HashMap::new(),
+                })
+            }
+        }
+    }
+}
+
+impl Display for SettingsFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Converts YAML into JSON. Non-string keys, like exit codes, are turned into strings.
+fn yaml_to_json(yaml: serde_norway::Value) -> Value {
+    match yaml {
+        serde_norway::Value::Null => Value::Null,
+        serde_norway::Value::Bool(b) => Value::Bool(b),
+        serde_norway::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            }
+        }
+        serde_norway::Value::String(s) => Value::String(s),
+        serde_norway::Value::Sequence(seq) => Value::Array(seq.into_iter().map(yaml_to_json).collect()),
+        serde_norway::Value::Mapping(mapping) => {
+            let mut obj = Map::new();
+            for (key, value) in mapping {
+                let key = match yaml_to_json(key) {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                obj.insert(key, yaml_to_json(value));
+            }
+            Value::Object(obj)
+        }
+        serde_norway::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+/// Converts TOML into JSON. Dates are kept as strings.
+fn toml_to_json(toml: toml::Value) -> Value {
+    match toml {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(arr) => Value::Array(arr.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => {
+            Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+/// Converts a byte offset into a line and column, starting at 1
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// The state of a service or a single test
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Warning,
+    Critical,
+    /// The test ran, but the outcome couldn't be determined (e.g. unparsable output)
+    Unknown,
+    /// The test hasn't run yet
+    Pending,
+    /// The test was deliberately not run (e.g. no internet)
+    Skipped,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Critical => "critical",
+            Status::Unknown => "unknown",
+            Status::Pending => "pending",
+            Status::Skipped => "skipped",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(status: &str) -> Option<Status> {
+        match status {
+            "ok" => Some(Status::Ok),
+            "warning" => Some(Status::Warning),
+            "critical" => Some(Status::Critical),
+            "unknown" => Some(Status::Unknown),
+            "pending" => Some(Status::Pending),
+            "skipped" => Some(Status::Skipped),
+            _ => None,
+        }
+    }
+
+    /// Whether this status needs attention, i.e. warning, critical or unknown
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Status::Warning | Status::Critical | Status::Unknown)
+    }
+
+    /// Whether a run with this status means the service is no longer down, i.e. ok or warning.
+    /// Resolves the incident of the service and clears its acknowledgement.
+    pub fn is_recovered(&self) -> bool {
+        matches!(self, Status::Ok | Status::Warning)
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Maps a success score onto a `Status`.
+///
+/// A score of at least `ok` is ok, at least `warning` is a warning, anything below is critical.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct StatusThresholds {
+    pub ok: f64,
+    pub warning: f64,
+}
+
+impl StatusThresholds {
+    /// Reads the thresholds from a `{"ok": 1.0, "warning": 0.5}` object, using `default` for missing keys.
+    pub fn from_value(value: &Value, default: &StatusThresholds) -> StatusThresholds {
+        StatusThresholds {
+            ok: value
+                .get("ok")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(default.ok),
+            warning: value
+                .get("warning")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(default.warning),
+        }
+    }
+
+    pub fn status_of(&self, successes: f64) -> Status {
+        if successes >= self.ok {
+            Status::Ok
+        } else if successes >= self.warning {
+            Status::Warning
+        } else {
+            Status::Critical
+        }
+    }
+}
+
+impl Display for StatusThresholds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ok >= {}, warning >= {}", self.ok, self.warning)
+    }
+}
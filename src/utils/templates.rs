@@ -0,0 +1,286 @@
+use serde_json::{Map, Value};
+
+/// A service after applying its template and expanding its matrix
+pub struct ExpandedService {
+    /// Path of the service entry it was expanded from, e.g. `services[2]`
+    pub path: String,
+    pub value: Value,
+}
+
+/// Expands the `services` of a settings file, applying templates and matrices.
+///
+/// A service can reference one of the `templates` with `template`, its own keys overriding the template.
+/// `{{param}}` placeholders in any string are replaced by the `params` of the service, and a `matrix`
+/// of parameter lists expands the service into one service for every combination.
+///
+/// # Arguments
+///
+/// * `json` - The settings file.
+/// * `templates` - The templates available to the services.
+///
+/// # Returns
+///
+/// The expanded services, along with the path and message of every problem found while expanding them.
+/// Services with problems are left out.
+pub fn expand_services(json: &Value, templates: &Map<String, Value>) -> (Vec<ExpandedService>, Vec<(String, String)>) {
+    let mut expanded: Vec<ExpandedService> = vec![];
+    let mut problems: Vec<(String, String)> = vec![];
+
+    let services = json.get("services").and_then(|v| v.as_array());
+    for (i, service) in services.into_iter().flatten().enumerate() {
+        let path = format!("services[{}]", i);
+        match expand_service(service, templates) {
+            Ok(values) => expanded.extend(values.into_iter().map(|value| ExpandedService {
+                path: path.clone(),
+                value,
+            })),
+            Err((key, message)) => {
+                let problem_path = if key.is_empty() { path } else { format!("{}.{}", path, key) };
+                problems.push((problem_path, message));
+            }
+        }
+    }
+    (expanded, problems)
+}
+
+/// Collects the `templates` of a settings file on top of the templates it already has access to
+pub fn merge_templates(json: &Value, templates: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = templates.clone();
+    if let Some(own) = json.get("templates").and_then(|v| v.as_object()) {
+        merged.extend(own.clone());
+    }
+    merged
+}
+
+/// Expands a single service entry, returning the key and message of the problem if it can't be expanded
+fn expand_service(service: &Value, templates: &Map<String, Value>) -> Result<Vec<Value>, (String, String)> {
+    let Some(obj) = service.as_object() else {
+        // Left for the validation to complain about
+        return Ok(vec![service.clone()]);
+    };
+    if !["template", "params", "matrix"].iter().any(|k| obj.contains_key(*k)) {
+        return Ok(vec![service.clone()]);
+    }
+
+    let mut merged = match obj.get("template") {
+        None => Map::new(),
+        Some(Value::String(name)) => match templates.get(name) {
+            Some(Value::Object(template)) => template.clone(),
+            Some(other) => return Err(("template".to_string(), format!("template \"{}\" must be an object, got {}", name, other))),
+            None => {
+                let known: Vec<&str> = templates.keys().map(String::as_str).collect();
+                return Err(("template".to_string(), format!("unknown template \"{}\", known templates are: {}", name, known.join(", "))));
+            }
+        },
+        Some(other) => return Err(("template".to_string(), format!("must be the name of a template, got {}", other))),
+    };
+    for key in ["template", "params", "matrix"] {
+        if merged.contains_key(key) {
+            return Err(("template".to_string(), format!("templates can't contain \"{}\"", key)));
+        }
+    }
+    for (key, value) in obj {
+        if !matches!(key.as_str(), "template" | "params" | "matrix") {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    let params = match obj.get("params") {
+        None => Map::new(),
+        Some(Value::Object(params)) => params.clone(),
+        Some(other) => return Err(("params".to_string(), format!("must be an object like {{\"url\": \"https://example.com\"}}, got {}", other))),
+    };
+
+    // Every combination of the matrix values
+    let mut combinations: Vec<Map<String, Value>> = vec![Map::new()];
+    match obj.get("matrix") {
+        None => {}
+        Some(Value::Object(matrix)) => {
+            for (key, values) in matrix {
+                let values = match values {
+                    Value::Array(values) if !values.is_empty() => values,
+                    _ => return Err((format!("matrix.{}", key), format!("must be a non-empty array, got {}", values))),
+                };
+                combinations = combinations
+                    .iter()
+                    .flat_map(|combination| {
+                        values.iter().map(move |value| {
+                            let mut combination = combination.clone();
+                            combination.insert(key.clone(), value.clone());
+                            combination
+                        })
+                    })
+                    .collect();
+            }
+        }
+        Some(other) => return Err(("matrix".to_string(), format!("must be an object like {{\"host\": [\"a\", \"b\"]}}, got {}", other))),
+    }
+
+    let merged = Value::Object(merged);
+    combinations
+        .into_iter()
+        .map(|combination| {
+            // The params can use the matrix values themselves, e.g. `"url": "https://{{host}}/"`
+            let mut all = match substitute(&Value::Object(params.clone()), &combination) {
+                Ok(Value::Object(all)) => all,
+                Ok(_) => Map::new(),
+                Err(message) => return Err(("params".to_string(), message)),
+            };
+            all.extend(combination);
+            substitute(&merged, &all).map_err(|message| (String::new(), message))
+        })
+        .collect()
+}
+
+/// Replaces the `{{param}}` placeholders in every string of the value.
+/// A string consisting of a single placeholder is replaced by the parameter as is, keeping numbers numbers.
+fn substitute(value: &Value, params: &Map<String, Value>) -> Result<Value, String> {
+    match value {
+        Value::String(s) => {
+            if let Some(name) = s.strip_prefix("{{").and_then(|s| s.strip_suffix("}}"))
+                && !name.contains("{{")
+            {
+                return lookup(name, params).cloned();
+            }
+            let mut substituted = String::new();
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(end) = rest[start..].find("}}") else {
+                    break;
+                };
+                substituted.push_str(&rest[..start]);
+                match lookup(&rest[start + 2..start + end], params)? {
+                    Value::String(param) => substituted.push_str(param),
+                    param => substituted.push_str(&param.to_string()),
+                }
+                rest = &rest[start + end + 2..];
+            }
+            substituted.push_str(rest);
+            Ok(Value::String(substituted))
+        }
+        Value::Array(arr) => arr.iter().map(|v| substitute(v, params)).collect::<Result<_, _>>().map(Value::Array),
+        Value::Object(obj) => obj
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), substitute(v, params)?)))
+            .collect::<Result<_, String>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn lookup<'a>(name: &str, params: &'a Map<String, Value>) -> Result<&'a Value, String> {
+    let name = name.trim();
+    params
+        .get(name)
+        .ok_or_else(|| format!("unknown parameter \"{{{{{}}}}}\", it's not in the params or matrix", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn expand(services: Value, templates: Value) -> (Vec<ExpandedService>, Vec<(String, String)>) {
+        let json = json!({"services": services, "templates": templates});
+        expand_services(&json, &merge_templates(&json, &Map::new()))
+    }
+
+    fn names(expanded: &[ExpandedService]) -> Vec<&str> {
+        expanded.iter().map(|s| s.value["name"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn expands_a_matrix_into_every_combination() {
+        let (expanded, problems) = expand(
+            json!([{
+                "name": "{{host}}:{{port}}",
+                "command": "check_tcp",
+                "args": ["-H", "{{host}}", "-p", "{{port}}"],
+                "matrix": {"host": ["a", "b", "c"], "port": [80, 443]}
+            }]),
+            json!({}),
+        );
+        assert!(problems.is_empty());
+        let mut names = names(&expanded);
+        names.sort();
+        assert_eq!(names, ["a:443", "a:80", "b:443", "b:80", "c:443", "c:80"]);
+        assert!(expanded.iter().all(|s| s.path == "services[0]"));
+        // A placeholder on its own keeps the type of the parameter
+        let service = expanded.iter().find(|s| s.value["name"] == "b:443").unwrap();
+        assert_eq!(service.value["args"], json!(["-H", "b", "-p", 443]));
+    }
+
+    #[test]
+    fn a_matrix_with_a_single_value_per_key_expands_to_one_service() {
+        let (expanded, _) = expand(
+            json!([{"name": "{{region}}-{{size}}", "command": "true", "matrix": {"region": ["eu"], "size": ["large"]}}]),
+            json!({}),
+        );
+        assert_eq!(names(&expanded), ["eu-large"]);
+    }
+
+    #[test]
+    fn params_can_use_the_matrix_values() {
+        let (expanded, problems) = expand(
+            json!([{
+                "name": "site-{{host}}",
+                "template": "website",
+                "params": {"url": "https://{{host}}/health"},
+                "matrix": {"host": ["one.example.com", "two.example.com"]},
+                "interval": 60
+            }]),
+            json!({"website": {"command": "check_website", "args": ["{{url}}"], "interval": 300, "timeout": 10}}),
+        );
+        assert!(problems.is_empty());
+        assert_eq!(expanded.len(), 2);
+        assert_eq!(expanded[0].value["args"], json!(["https://one.example.com/health"]));
+        assert_eq!(expanded[1].value["args"], json!(["https://two.example.com/health"]));
+        // The service overrides its template
+        assert_eq!(expanded[0].value["interval"], json!(60));
+        assert_eq!(expanded[0].value["timeout"], json!(10));
+        assert!(expanded[0].value.get("matrix").is_none());
+    }
+
+    #[test]
+    fn leaves_services_without_templates_alone() {
+        let service = json!({"name": "{{not a param}}", "command": "true"});
+        let (expanded, problems) = expand(json!([service.clone()]), json!({}));
+        assert!(problems.is_empty());
+        assert_eq!(expanded[0].value, service);
+    }
+
+    #[test]
+    fn reports_services_that_cant_be_expanded() {
+        let (expanded, problems) = expand(
+            json!([
+                {"name": "empty", "command": "true", "matrix": {"host": []}},
+                {"name": "flat", "command": "true", "matrix": ["a", "b"]},
+                {"name": "{{host}}-{{port}}", "command": "true", "matrix": {"host": ["a"]}},
+                {"name": "missing", "template": "nope"},
+                {"name": "nested", "template": "nested"},
+                {"name": "ok", "command": "true"}
+            ]),
+            json!({"nested": {"command": "true", "matrix": {"host": ["a"]}}}),
+        );
+        assert_eq!(names(&expanded), ["ok"]);
+        assert_eq!(
+            problems,
+            [
+                ("services[0].matrix.host".to_string(), "must be a non-empty array, got []".to_string()),
+                (
+                    "services[1].matrix".to_string(),
+                    "must be an object like {\"host\": [\"a\", \"b\"]}, got [\"a\",\"b\"]".to_string()
+                ),
+                (
+                    "services[2]".to_string(),
+                    "unknown parameter \"{{port}}\", it's not in the params or matrix".to_string()
+                ),
+                (
+                    "services[3].template".to_string(),
+                    "unknown template \"nope\", known templates are: nested".to_string()
+                ),
+                ("services[4].template".to_string(), "templates can't contain \"matrix\"".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+/// Parses a point in time given to a command, e.g. for filtering the audit log.
+///
+/// Accepts RFC 3339 (`2024-05-01T12:00:00Z`), a date (`2024-05-01`, midnight UTC), a unix timestamp,
+/// or a duration ago like `30m`, `12h` or `7d`.
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Some(time) = parse_absolute_time(s)? {
+        return Ok(time);
+    }
+    match parse_duration(s) {
+        Ok(duration) => Ok(Utc::now() - duration),
+        Err(_) => Err(format!(
+            "invalid time \"{}\", expected e.g. 2024-05-01T12:00:00Z, 2024-05-01, a unix timestamp or 30m/12h/7d ago",
+            s
+        )),
+    }
+}
+
+/// Parses a point in time in the future given to a command, e.g. until when a service is acknowledged.
+///
+/// Accepts the same as `parse_time`, except that durations like `30m`, `12h` or `7d` are from now on.
+pub fn parse_deadline(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Some(time) = parse_absolute_time(s)? {
+        return Ok(time);
+    }
+    match parse_duration(s) {
+        Ok(duration) => Ok(Utc::now() + duration),
+        Err(_) => Err(format!(
+            "invalid time \"{}\", expected e.g. 2024-05-01T12:00:00Z, 2024-05-01, a unix timestamp or 30m/12h/7d from now",
+            s
+        )),
+    }
+}
+
+/// Parses a duration given to a command, e.g. `30s`, `30m`, `12h` or `7d`, which has to be more than zero
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let n = s.get(..s.len().saturating_sub(1)).and_then(|n| n.parse::<i64>().ok()).filter(|n| *n > 0);
+    let duration = match (n, s.chars().last()) {
+        (Some(n), Some('s')) => Duration::try_seconds(n),
+        (Some(n), Some('m')) => Duration::try_minutes(n),
+        (Some(n), Some('h')) => Duration::try_hours(n),
+        (Some(n), Some('d')) => Duration::try_days(n),
+        _ => None,
+    };
+    duration.ok_or(format!("invalid duration \"{}\", expected a positive amount like 30s, 30m, 12h or 7d", s))
+}
+
+/// Parses RFC 3339, a date or a unix timestamp, `None` if it's neither of them
+fn parse_absolute_time(s: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(Some(time.with_timezone(&Utc)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(Some(date.and_time(NaiveTime::MIN).and_utc()));
+    }
+    if let Ok(timestamp) = s.parse::<i64>() {
+        return DateTime::from_timestamp(timestamp, 0)
+            .map(Some)
+            .ok_or(format!("{} is out of range", s));
+    }
+    Ok(None)
+}
@@ -0,0 +1,996 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::badges;
+use crate::service::env_value;
+use crate::settings::parse_permissions;
+use crate::utils::exit_codes::ExitCodeValue;
+use crate::utils::interpolation::interpolate;
+use crate::utils::output_format::OutputFormat;
+use crate::utils::partial_success::PartialSuccess;
+use crate::utils::protocol::Protocol;
+use crate::utils::retry_strategy::RetryStrategy;
+use crate::status_page::PUBLIC_FIELDS;
+use crate::utils::templates::expand_services;
+use crate::zmq_handler::access::COMMANDS;
+use crate::zmq_handler::curve::{curve_supported, is_curve_key};
+
+const SETTINGS_KEYS: &[&str] = &[
+    "protocol",
+    "port",
+    "bind",
+    "endpoints",
+    "ipc_permissions",
+    "interval",
+    "timeout",
+    "pause_on_no_internet",
+    "retry_counter",
+    "retry_strategy",
+    "max_output_bytes",
+    "stderr_is_warning",
+    "thresholds",
+    "partial_success",
+    "include",
+    "templates",
+    "curve",
+    "access",
+    "audit_log",
+    "history",
+    "status_page",
+    "badges",
+    "incidents_file",
+    "services",
+];
+
+/// Keys of the main settings file that can't be set in included files
+const MAIN_ONLY_KEYS: &[&str] = &[
+    "protocol",
+    "port",
+    "bind",
+    "endpoints",
+    "ipc_permissions",
+    "include",
+    "curve",
+    "access",
+    "audit_log",
+    "history",
+    "status_page",
+    "badges",
+    "incidents_file",
+];
+
+const ENDPOINT_TRANSPORTS: &[&str] = &["tcp", "ipc", "pgm", "epgm"];
+
+const CURVE_KEYS: &[&str] = &["secret_key", "allowed_clients"];
+
+const ACCESS_KEYS: &[&str] = &["roles", "clients", "default_role"];
+
+const ACCESS_CLIENT_KEYS: &[&str] = &["name", "key", "token", "role"];
+
+const SERVICE_KEYS: &[&str] = &[
+    "name",
+    "tags",
+    "group",
+    "command",
+    "args",
+    "env",
+    "interval",
+    "timeout",
+    "pause_on_no_internet",
+    "retry_counter",
+    "retry_strategy",
+    "max_output_bytes",
+    "stderr_is_warning",
+    "format",
+    "report",
+    "exit_codes",
+    "thresholds",
+    "partial_success",
+    "public",
+];
+
+const HISTORY_KEYS: &[&str] = &["file", "retention_days"];
+
+const STATUS_PAGE_KEYS: &[&str] = &["directory", "title", "days"];
+
+const BADGES_KEYS: &[&str] = &["directory", "days"];
+
+const PUBLIC_KEYS: &[&str] = &["name", "tests", "hide"];
+
+const THRESHOLDS_KEYS: &[&str] = &["ok", "warning"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A single problem found in the settings file
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub file: String,
+    /// JSON path of the problem, e.g. `services[1].retry_strategy`. Empty for the whole file.
+    pub path: String,
+    /// Line and column, starting at 1
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn error(file: &str, path: &str, location: Option<(usize, usize)>, message: String) -> Self {
+        Problem {
+            severity: Severity::Error,
+            file: file.to_string(),
+            path: path.to_string(),
+            location,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.file)?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, ": {}", self.path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Whether any of the problems is an error
+pub fn has_errors(problems: &[Problem]) -> bool {
+    problems.iter().any(|p| p.severity == Severity::Error)
+}
+
+/// Validates the settings files, collecting every problem instead of stopping at the first one.
+///
+/// The main settings file and the files it includes are validated one by one,
+/// sharing the service names to catch duplicates across files.
+#[derive(Default)]
+pub struct Validation {
+    /// The services already seen by name, along with the file and path they were defined in
+    names: HashMap<String, String>,
+    /// The badge files already written by a service, along with where it was defined. `None` without badges
+    badge_files: Option<HashMap<String, String>>,
+    pub problems: Vec<Problem>,
+}
+
+impl Validation {
+    /// Validates a single settings file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The path of the settings file, used in the problems.
+    /// * `locations` - Line and column of the keys and values in the settings file by path, see `json_locator::locate`.
+    /// * `json` - The parsed settings.
+    /// * `included` - Whether the file is included by the main settings file, those can only contain services, templates and defaults.
+    /// * `templates` - The templates available to the services of the file, see `templates::merge_templates`.
+    pub fn file(
+        &mut self,
+        file: &str,
+        locations: HashMap<String, (usize, usize)>,
+        json: &Value,
+        included: bool,
+        templates: &Map<String, Value>,
+    ) {
+        let mut validator = Validator {
+            file,
+            locations,
+            names: &mut self.names,
+            badge_files: &mut self.badge_files,
+            problems: &mut self.problems,
+        };
+        validator.settings(json, included, templates);
+    }
+
+    /// Reports a problem found outside of the validation, e.g. an included file that can't be read
+    pub fn push(&mut self, problem: Problem) {
+        self.problems.push(problem);
+    }
+}
+
+struct Validator<'a> {
+    file: &'a str,
+    locations: HashMap<String, (usize, usize)>,
+    names: &'a mut HashMap<String, String>,
+    badge_files: &'a mut Option<HashMap<String, String>>,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, severity: Severity, path: &str, message: String) {
+        // Services expanded from the same matrix share their problems
+        if self.problems.iter().any(|p| p.file == self.file && p.path == path && p.message == message) {
+            return;
+        }
+        self.problems.push(Problem {
+            severity,
+            file: self.file.to_string(),
+            path: path.to_string(),
+            location: self.locations.get(path).copied(),
+            message,
+        });
+    }
+
+    fn error(&mut self, path: &str, message: String) {
+        self.report(Severity::Error, path, message);
+    }
+
+    fn warning(&mut self, path: &str, message: String) {
+        self.report(Severity::Warning, path, message);
+    }
+
+    fn settings(&mut self, json: &Value, included: bool, templates: &Map<String, Value>) {
+        let Some(obj) = json.as_object() else {
+            self.error("", "settings must be an object (a mapping in YAML, a table in TOML)".to_string());
+            return;
+        };
+        if included {
+            for key in MAIN_ONLY_KEYS {
+                if obj.contains_key(*key) {
+                    self.error(key, format!("\"{}\" can only be set in the main settings file", key));
+                }
+            }
+        }
+        self.unknown_keys("", obj, SETTINGS_KEYS);
+
+        self.one_of("protocol", obj.get("protocol"), &["tcp", "ipc", "pgm", "epgm"], |s| {
+            Protocol::from_str(s).is_some()
+        });
+        if let Some(port) = obj.get("port")
+            && port.as_u64().is_none_or(|p| p > u16::MAX as u64)
+        {
+            self.error("port", format!("must be a port number between 0 and {}, got {}", u16::MAX, port));
+        }
+        self.endpoints(obj);
+        match obj.get("include") {
+            None => {}
+            Some(Value::Array(patterns)) => {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if !pattern.is_string() {
+                        self.error(&format!("include[{}]", i), format!("must be a glob pattern, got {}", pattern));
+                    }
+                }
+            }
+            Some(other) => self.error("include", format!("must be an array of glob patterns, got {}", other)),
+        }
+        if let Some(curve) = obj.get("curve") {
+            self.curve(curve);
+        }
+        if let Some(access) = obj.get("access") {
+            self.access(access, obj.contains_key("curve"));
+        }
+        if let Some(audit_log) = obj.get("audit_log") {
+            self.file_path("audit_log", audit_log);
+        }
+        if let Some(history) = obj.get("history") {
+            self.history(history);
+        }
+        if let Some(status_page) = obj.get("status_page") {
+            self.status_page(status_page, obj.contains_key("history"));
+        }
+        if let Some(badges) = obj.get("badges") {
+            self.badges(badges);
+            self.badge_files.get_or_insert_default();
+        }
+        if let Some(incidents_file) = obj.get("incidents_file") {
+            self.file_path("incidents_file", incidents_file);
+        }
+        self.common("", obj);
+
+        match obj.get("services") {
+            None if !included && !obj.contains_key("include") => self.warning("", "no services defined".to_string()),
+            None => {}
+            Some(Value::Array(_)) => {
+                // Services are validated after applying their template and expanding their matrix
+                let (services, problems) = expand_services(json, templates);
+                for (path, message) in problems {
+                    self.error(&path, message);
+                }
+                for service in services {
+                    self.service(&service.path, &service.value);
+                }
+            }
+            Some(other) => self.error("services", format!("must be an array of services, got {}", other)),
+        }
+        match obj.get("templates") {
+            None => {}
+            Some(Value::Object(templates)) => {
+                for (name, template) in templates {
+                    if !template.is_object() {
+                        self.error(&format!("templates.{}", name), format!("a template must be an object, got {}", template));
+                    }
+                }
+            }
+            Some(other) => self.error("templates", format!("must be an object of templates by name, got {}", other)),
+        }
+    }
+
+    fn endpoints(&mut self, obj: &Map<String, Value>) {
+        if let Some(bind) = obj.get("bind")
+            && bind.as_str().is_none_or(|b| b.is_empty())
+        {
+            self.error("bind", format!("must be an address like \"127.0.0.1\" or \"*\", got {}", bind));
+        }
+        match obj.get("endpoints") {
+            None => {
+                if let Some(protocol) = obj.get("protocol").and_then(|p| p.as_str())
+                    && protocol != "tcp"
+                {
+                    self.warning(
+                        "protocol",
+                        format!("{} needs a path or multicast address, set \"endpoints\" instead, e.g. \"ipc:///run/status.sock\"", protocol),
+                    );
+                }
+            }
+            Some(Value::Array(endpoints)) => {
+                if endpoints.is_empty() {
+                    self.error("endpoints", "needs at least one endpoint".to_string());
+                }
+                for (i, endpoint) in endpoints.iter().enumerate() {
+                    let valid = endpoint.as_str().and_then(|e| e.split_once("://")).is_some_and(|(transport, address)| {
+                        ENDPOINT_TRANSPORTS.contains(&transport) && !address.is_empty()
+                    });
+                    if !valid {
+                        self.error(
+                            &format!("endpoints[{}]", i),
+                            format!("must be a ZMQ endpoint like \"tcp://127.0.0.1:5747\" or \"ipc:///run/status.sock\", got {}", endpoint),
+                        );
+                    }
+                }
+                for key in ["protocol", "port", "bind"] {
+                    if obj.contains_key(key) {
+                        self.warning(key, format!("\"{}\" is ignored when \"endpoints\" are set", key));
+                    }
+                }
+            }
+            Some(other) => self.error("endpoints", format!("must be an array of ZMQ endpoints, got {}", other)),
+        }
+        if let Some(permissions) = obj.get("ipc_permissions")
+            && permissions.as_str().and_then(parse_permissions).is_none()
+        {
+            self.error("ipc_permissions", format!("must be octal file permissions like \"0660\", got {}", permissions));
+        }
+    }
+
+    /// Checks that the value is the path of a file in an existing directory
+    fn file_path(&mut self, path: &str, value: &Value) {
+        let Some(file) = value.as_str().filter(|p| !p.is_empty()) else {
+            self.error(path, format!("must be the path of a file, got {}", value));
+            return;
+        };
+        let directory = Path::new(file).parent().filter(|d| !d.as_os_str().is_empty());
+        if let Some(directory) = directory
+            && !directory.is_dir()
+        {
+            self.error(path, format!("the directory {} doesn't exist", directory.display()));
+        }
+    }
+
+    fn history(&mut self, history: &Value) {
+        let Some(obj) = history.as_object() else {
+            self.error("history", format!("must be an object like {{\"file\": \"history.jsonl\"}}, got {}", history));
+            return;
+        };
+        self.unknown_keys("history", obj, HISTORY_KEYS);
+        match obj.get("file") {
+            None => self.error("history", "missing required key \"file\"".to_string()),
+            Some(file) => self.file_path("history.file", file),
+        }
+        if let Some(days) = obj.get("retention_days")
+            && days.as_u64().is_none_or(|d| d == 0 || d > u32::MAX as u64)
+        {
+            self.error("history.retention_days", format!("must be a whole number of days above 0, got {}", days));
+        }
+    }
+
+    fn status_page(&mut self, status_page: &Value, history: bool) {
+        let Some(obj) = status_page.as_object() else {
+            self.error("status_page", format!("must be an object like {{\"directory\": \"/var/www/status\"}}, got {}", status_page));
+            return;
+        };
+        self.unknown_keys("status_page", obj, STATUS_PAGE_KEYS);
+        match obj.get("directory") {
+            None => self.error("status_page", "missing required key \"directory\"".to_string()),
+            Some(Value::String(directory)) if !directory.is_empty() => {}
+            Some(other) => self.error("status_page.directory", format!("must be the path of a directory, got {}", other)),
+        }
+        if let Some(title) = obj.get("title")
+            && !title.is_string()
+        {
+            self.error("status_page.title", format!("must be a string, got {}", title));
+        }
+        if let Some(days) = obj.get("days")
+            && days.as_u64().is_none_or(|d| d == 0 || d > 366)
+        {
+            self.error("status_page.days", format!("must be a whole number of days between 1 and 366, got {}", days));
+        }
+        if !history {
+            self.warning("status_page", "the uptime bars stay empty without a \"history\"".to_string());
+        }
+    }
+
+    fn badges(&mut self, badges: &Value) {
+        let Some(obj) = badges.as_object() else {
+            self.error("badges", format!("must be an object like {{\"directory\": \"/var/www/badges\"}}, got {}", badges));
+            return;
+        };
+        self.unknown_keys("badges", obj, BADGES_KEYS);
+        match obj.get("directory") {
+            None => self.error("badges", "missing required key \"directory\"".to_string()),
+            Some(Value::String(directory)) if !directory.is_empty() => {}
+            Some(other) => self.error("badges.directory", format!("must be the path of a directory, got {}", other)),
+        }
+        if let Some(days) = obj.get("days")
+            && days.as_u64().is_none_or(|d| d == 0 || d > u32::MAX as u64)
+        {
+            self.error("badges.days", format!("must be a whole number of days above 0, got {}", days));
+        }
+    }
+
+    fn public(&mut self, path: &str, public: &Value) {
+        let obj = match public {
+            Value::Bool(_) => return,
+            Value::Object(obj) => obj,
+            other => {
+                self.error(path, format!("must be true, false or an object like {{\"tests\": [\"*\"]}}, got {}", other));
+                return;
+            }
+        };
+        self.unknown_keys(path, obj, PUBLIC_KEYS);
+        if let Some(name) = obj.get("name")
+            && name.as_str().is_none_or(|n| n.is_empty())
+        {
+            self.error(&format!("{}.name", path), format!("must be a non-empty string, got {}", name));
+        }
+        for key in ["tests", "hide"] {
+            match obj.get(key) {
+                None => {}
+                Some(Value::Array(values)) => {
+                    for (i, value) in values.iter().enumerate() {
+                        let value_path = format!("{}.{}[{}]", path, key, i);
+                        match value.as_str() {
+                            Some(field) if key == "hide" && !PUBLIC_FIELDS.contains(&field) => {
+                                self.error(&value_path, format!("must be one of {}, got {}", PUBLIC_FIELDS.join(", "), value))
+                            }
+                            Some(_) => {}
+                            None => self.error(&value_path, format!("must be a string, got {}", value)),
+                        }
+                    }
+                }
+                Some(other) => self.error(&format!("{}.{}", path, key), format!("must be an array of strings, got {}", other)),
+            }
+        }
+    }
+
+    fn curve(&mut self, curve: &Value) {
+        let Some(obj) = curve.as_object() else {
+            self.error("curve", format!("must be an object like {{\"secret_key\": \"${{file:server.key}}\"}}, got {}", curve));
+            return;
+        };
+        self.unknown_keys("curve", obj, CURVE_KEYS);
+        if !curve_supported() {
+            self.error("curve", "libzmq was built without CURVE support".to_string());
+        }
+        match obj.get("secret_key") {
+            None => self.error("curve", "missing required key \"secret_key\"".to_string()),
+            Some(Value::String(secret_key)) => match interpolate(secret_key) {
+                Ok(key) if is_curve_key(key.trim()) => {}
+                Ok(_) => self.error("curve.secret_key", "must be a z85 encoded key of 40 characters, see `status keygen`".to_string()),
+                Err(message) => self.error("curve.secret_key", message),
+            },
+            Some(other) => self.error("curve.secret_key", format!("must be a string, got {}", other)),
+        }
+        match obj.get("allowed_clients") {
+            None => {}
+            Some(Value::Array(keys)) => {
+                for (i, key) in keys.iter().enumerate() {
+                    if !key.as_str().is_some_and(is_curve_key) {
+                        self.error(
+                            &format!("curve.allowed_clients[{}]", i),
+                            format!("must be a z85 encoded public key of 40 characters, got {}", key),
+                        );
+                    }
+                }
+            }
+            Some(other) => self.error("curve.allowed_clients", format!("must be an array of public keys, got {}", other)),
+        }
+    }
+
+    fn access(&mut self, access: &Value, curve: bool) {
+        let Some(obj) = access.as_object() else {
+            self.error("access", format!("must be an object with roles and clients, got {}", access));
+            return;
+        };
+        self.unknown_keys("access", obj, ACCESS_KEYS);
+
+        let mut roles: Vec<&str> = vec![];
+        match obj.get("roles") {
+            None => self.error("access", "missing required key \"roles\"".to_string()),
+            Some(Value::Object(role_map)) => {
+                for (role, commands) in role_map {
+                    roles.push(role);
+                    let Some(commands) = commands.as_array() else {
+                        self.error(&format!("access.roles.{}", role), format!("must be an array of commands, got {}", commands));
+                        continue;
+                    };
+                    for (i, command) in commands.iter().enumerate() {
+                        if !command.as_str().is_some_and(|c| c == "*" || COMMANDS.contains(&c)) {
+                            self.error(
+                                &format!("access.roles.{}[{}]", role, i),
+                                format!("must be one of *, {}, got {}", COMMANDS.join(", "), command),
+                            );
+                        }
+                    }
+                }
+            }
+            Some(other) => self.error("access.roles", format!("must be an object like {{\"viewer\": [\"service\"]}}, got {}", other)),
+        }
+
+        let check_role = |validator: &mut Self, path: &str, role: &Value| match role.as_str() {
+            Some(role) if roles.contains(&role) => {}
+            _ => validator.error(path, format!("must be one of the roles: {}, got {}", roles.join(", "), role)),
+        };
+        if let Some(default_role) = obj.get("default_role") {
+            check_role(self, "access.default_role", default_role);
+        }
+        match obj.get("clients") {
+            None => {}
+            Some(Value::Array(clients)) => {
+                for (i, client) in clients.iter().enumerate() {
+                    let path = format!("access.clients[{}]", i);
+                    let Some(client) = client.as_object() else {
+                        self.error(&path, format!("a client must be an object, got {}", client));
+                        continue;
+                    };
+                    self.unknown_keys(&path, client, ACCESS_CLIENT_KEYS);
+                    match client.get("role") {
+                        Some(role) => check_role(self, &format!("{}.role", path), role),
+                        None => self.error(&path, "missing required key \"role\"".to_string()),
+                    }
+                    match (client.get("key"), client.get("token")) {
+                        (Some(_), Some(_)) | (None, None) => {
+                            self.error(&path, "a client needs either a \"key\" or a \"token\"".to_string())
+                        }
+                        (Some(key), None) => {
+                            if !key.as_str().is_some_and(is_curve_key) {
+                                self.error(&format!("{}.key", path), format!("must be a z85 encoded public key of 40 characters, got {}", key));
+                            } else if !curve {
+                                self.warning(&format!("{}.key", path), "keys only identify clients when \"curve\" is enabled".to_string());
+                            }
+                        }
+                        (None, Some(token)) => match token.as_str().map(interpolate) {
+                            Some(Ok(token)) if !token.is_empty() => {}
+                            Some(Ok(_)) => self.error(&format!("{}.token", path), "can't be empty".to_string()),
+                            Some(Err(message)) => self.error(&format!("{}.token", path), message),
+                            None => self.error(&format!("{}.token", path), format!("must be a string, got {}", token)),
+                        },
+                    }
+                }
+            }
+            Some(other) => self.error("access.clients", format!("must be an array of clients, got {}", other)),
+        }
+    }
+
+    /// Validates the keys that can be set both globally and per service
+    fn common(&mut self, prefix: &str, obj: &Map<String, Value>) {
+        let path = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+
+        // An interval of 0 would run the service over and over without ever waiting
+        if let Some(value) = obj.get("interval")
+            && value.as_u64().is_none_or(|v| v == 0)
+        {
+            self.error(&path("interval"), format!("must be a whole number of seconds above 0, got {}", value));
+        }
+        if let Some(value) = obj.get("max_output_bytes")
+            && value.as_u64().is_none()
+        {
+            self.error(&path("max_output_bytes"), format!("must be a whole number of 0 or more, got {}", value));
+        }
+        if let Some(value) = obj.get("retry_counter")
+            && value.as_i64().is_none_or(|v| v < 0)
+        {
+            self.error(&path("retry_counter"), format!("must be a whole number of 0 or more, got {}", value));
+        }
+        if let Some(value) = obj.get("timeout")
+            && !value.as_f64().is_some_and(|v| v > 0.0)
+        {
+            self.error(&path("timeout"), format!("must be a number of seconds above 0, got {}", value));
+        }
+        for key in ["pause_on_no_internet", "stderr_is_warning"] {
+            if let Some(value) = obj.get(key)
+                && !value.is_boolean()
+            {
+                self.error(&path(key), format!("must be true or false, got {}", value));
+            }
+        }
+        self.one_of(
+            &path("retry_strategy"),
+            obj.get("retry_strategy"),
+            &["best", "combined_best", "median", "worst", "combined_worst"],
+            |s| RetryStrategy::from_str(s).is_some(),
+        );
+        self.one_of(
+            &path("partial_success"),
+            obj.get("partial_success"),
+            &["weighted", "up", "down"],
+            |s| PartialSuccess::from_str(s).is_some(),
+        );
+
+        if let Some(thresholds) = obj.get("thresholds") {
+            let thresholds_path = path("thresholds");
+            match thresholds.as_object() {
+                Some(thresholds) => {
+                    self.unknown_keys(&thresholds_path, thresholds, THRESHOLDS_KEYS);
+                    for key in THRESHOLDS_KEYS {
+                        if let Some(value) = thresholds.get(*key)
+                            && !value.as_f64().is_some_and(|v| (0.00..=1.00).contains(&v))
+                        {
+                            self.error(
+                                &format!("{}.{}", thresholds_path, key),
+                                format!("must be a number between 0.00 and 1.00, got {}", value),
+                            );
+                        }
+                    }
+                    let ok = thresholds.get("ok").and_then(|v| v.as_f64());
+                    let warning = thresholds.get("warning").and_then(|v| v.as_f64());
+                    if let (Some(ok), Some(warning)) = (ok, warning)
+                        && warning > ok
+                    {
+                        self.error(&thresholds_path, format!("warning ({}) can't be above ok ({})", warning, ok));
+                    }
+                }
+                None => self.error(&thresholds_path, format!("must be an object like {{\"ok\": 1.0, \"warning\": 0.5}}, got {}", thresholds)),
+            }
+        }
+    }
+
+    fn service(&mut self, path: &str, service: &Value) {
+        let Some(obj) = service.as_object() else {
+            self.error(path, format!("a service must be an object, got {}", service));
+            return;
+        };
+        self.unknown_keys(path, obj, SERVICE_KEYS);
+
+        match obj.get("name") {
+            None => self.error(path, "missing required key \"name\"".to_string()),
+            Some(Value::String(name)) if name.is_empty() => self.error(&format!("{}.name", path), "can't be empty".to_string()),
+            Some(Value::String(name)) => {
+                let defined_at = format!("{} {}", self.file, path);
+                match self.names.insert(name.clone(), defined_at.clone()) {
+                    Some(previous) if previous == defined_at => self.error(
+                        &format!("{}.name", path),
+                        format!("duplicate service name \"{}\", use the matrix parameters in the name to make it unique", name),
+                    ),
+                    Some(previous) => self.error(
+                        &format!("{}.name", path),
+                        format!("duplicate service name \"{}\", already used by {}", name, previous),
+                    ),
+                    None => {}
+                }
+            }
+            Some(other) => self.error(&format!("{}.name", path), format!("must be a string, got {}", other)),
+        }
+        match obj.get("command") {
+            None => self.error(path, "missing required key \"command\"".to_string()),
+            Some(Value::String(command)) => self.interpolation(&format!("{}.command", path), command),
+            Some(other) => self.error(&format!("{}.command", path), format!("must be a string, got {}", other)),
+        }
+        match obj.get("args") {
+            None => {}
+            Some(Value::Array(args)) => {
+                for (i, arg) in args.iter().enumerate() {
+                    let arg_path = format!("{}.args[{}]", path, i);
+                    match arg {
+                        Value::String(arg) => self.interpolation(&arg_path, arg),
+                        _ => self.error(&arg_path, format!("must be a string, got {}", arg)),
+                    }
+                }
+            }
+            Some(other) => self.error(&format!("{}.args", path), format!("must be an array of strings, got {}", other)),
+        }
+        match obj.get("env") {
+            None => {}
+            Some(Value::Object(env)) => {
+                for (name, value) in env {
+                    let env_path = format!("{}.env.{}", path, name);
+                    match env_value(value) {
+                        Some(value) => self.interpolation(&env_path, &value),
+                        None => self.error(&env_path, format!("must be a string, got {}", value)),
+                    }
+                }
+            }
+            Some(other) => self.error(&format!("{}.env", path), format!("must be an object like {{\"NAME\": \"value\"}}, got {}", other)),
+        }
+        match obj.get("tags") {
+            None => {}
+            Some(Value::Array(tags)) => {
+                for (i, tag) in tags.iter().enumerate() {
+                    if tag.as_str().is_none_or(|t| t.is_empty()) {
+                        self.error(&format!("{}.tags[{}]", path, i), format!("must be a non-empty string, got {}", tag));
+                    }
+                }
+            }
+            Some(other) => self.error(&format!("{}.tags", path), format!("must be an array of strings, got {}", other)),
+        }
+        if let Some(group) = obj.get("group")
+            && group.as_str().is_none_or(|g| g.is_empty())
+        {
+            self.error(&format!("{}.group", path), format!("must be a non-empty string, got {}", group));
+        }
+        if let Some(report) = obj.get("report")
+            && !report.is_string()
+        {
+            self.error(&format!("{}.report", path), format!("must be a file path, got {}", report));
+        }
+        self.one_of(
+            &format!("{}.format", path),
+            obj.get("format"),
+            &["auto", "json", "plain", "exit_code", "jsonl", "nagios", "tap", "junit"],
+            |s| OutputFormat::from_str(s).is_some(),
+        );
+        match obj.get("exit_codes") {
+            None => {}
+            Some(Value::Object(exit_codes)) => {
+                for (code, value) in exit_codes {
+                    let code_path = format!("{}.exit_codes.{}", path, code);
+                    if code.trim().parse::<i32>().is_err() {
+                        self.error(&code_path, format!("\"{}\" is not an exit code", code));
+                    }
+                    if ExitCodeValue::from_value(value).is_none() {
+                        self.error(&code_path, format!("must be a number between 0.00 and 1.00, a boolean or \"unknown\", got {}", value));
+                    }
+                }
+            }
+            Some(other) => self.error(&format!("{}.exit_codes", path), format!("must be an object like {{\"1\": 0.5}}, got {}", other)),
+        }
+        if let Some(public) = obj.get("public") {
+            self.public(&format!("{}.public", path), public);
+        }
+        self.badge_file(path, obj);
+        self.common(path, obj);
+    }
+
+    /// Checks that the badge of a public service doesn't overwrite the badge of another one
+    fn badge_file(&mut self, path: &str, obj: &Map<String, Value>) {
+        let public = obj.get("public");
+        if public.and_then(|p| p.as_bool()) == Some(false) {
+            return;
+        }
+        let (name_path, name) = match public.and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+            Some(name) => (format!("{}.public.name", path), name),
+            None => match obj.get("name").and_then(|n| n.as_str()) {
+                Some(name) => (format!("{}.name", path), name),
+                None => return,
+            },
+        };
+        let file = format!("{}.svg", badges::file_name(name));
+        let defined_at = format!("{} {}", self.file, path);
+        let Some(files) = self.badge_files.as_mut() else {
+            return;
+        };
+        let previous = files.entry(file.clone()).or_insert(defined_at.clone()).clone();
+        if previous != defined_at {
+            self.error(
+                &name_path,
+                format!("the badge of \"{}\" would overwrite {} of {}, rename one of them", name, file, previous),
+            );
+        }
+    }
+
+    /// Checks that the `${...}` placeholders of a string can be filled in
+    fn interpolation(&mut self, path: &str, value: &str) {
+        if let Err(message) = interpolate(value) {
+            self.error(path, message);
+        }
+    }
+
+    /// Checks that an optional string value is one of the allowed values
+    fn one_of(&mut self, path: &str, value: Option<&Value>, allowed: &[&str], is_valid: impl Fn(&str) -> bool) {
+        let Some(value) = value else {
+            return;
+        };
+        if !value.as_str().is_some_and(is_valid) {
+            self.error(path, format!("must be one of {}, got {}", allowed.join(", "), value));
+        }
+    }
+
+    fn unknown_keys(&mut self, path: &str, obj: &Map<String, Value>, known: &[&str]) {
+        for key in obj.keys() {
+            if known.contains(&key.as_str()) {
+                continue;
+            }
+            let key_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            let message = match suggest(key, known) {
+                Some(suggestion) => format!("unknown key \"{}\", did you mean \"{}\"?", key, suggestion),
+                None => format!("unknown key \"{}\", it is ignored", key),
+            };
+            self.warning(&key_path, message);
+        }
+    }
+}
+
+/// Finds the known key closest to a misspelled one, if it's close enough
+fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|k| (edit_distance(key, k), *k))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, k)| k)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != *cb) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::json_locator::locate;
+    use crate::utils::templates::merge_templates;
+
+    fn validate_file(validation: &mut Validation, file: &str, json: &str) {
+        let value: Value = serde_json::from_str(json).unwrap();
+        validation.file(file, locate(json), &value, false, &merge_templates(&value, &Map::new()));
+    }
+
+    fn validate(json: &str) -> Vec<Problem> {
+        let mut validation = Validation::default();
+        validate_file(&mut validation, "settings.json", json);
+        validation.problems
+    }
+
+    fn messages(problems: &[Problem]) -> Vec<String> {
+        problems.iter().map(|p| format!("{}: {}: {}", p.severity, p.path, p.message)).collect()
+    }
+
+    #[test]
+    fn accepts_valid_settings() {
+        let problems = validate(r#"{"interval": 60, "services": [{"name": "web", "command": "true", "interval": 30}]}"#);
+        assert!(problems.is_empty(), "{:?}", messages(&problems));
+    }
+
+    #[test]
+    fn rejects_a_zero_interval() {
+        let problems = validate(r#"{"interval": 0, "services": [{"name": "web", "command": "true", "interval": 0}]}"#);
+        assert_eq!(
+            messages(&problems),
+            [
+                "error: interval: must be a whole number of seconds above 0, got 0",
+                "error: services[0].interval: must be a whole number of seconds above 0, got 0",
+            ]
+        );
+        // Unlike the interval, 0 means unlimited here
+        assert!(validate(r#"{"max_output_bytes": 0, "services": []}"#).is_empty());
+    }
+
+    #[test]
+    fn warns_about_unknown_keys() {
+        let problems = validate(
+            r#"{"intervall": 60, "colour": "red", "thresholds": {"ok": 1, "warnng": 0.5},
+                "services": [{"name": "web", "command": "true", "arg": ["-v"]}]}"#,
+        );
+        assert!(!has_errors(&problems));
+        let mut messages = messages(&problems);
+        messages.sort();
+        assert_eq!(
+            messages,
+            [
+                "warning: colour: unknown key \"colour\", it is ignored",
+                "warning: intervall: unknown key \"intervall\", did you mean \"interval\"?",
+                "warning: services[0].arg: unknown key \"arg\", did you mean \"args\"?",
+                "warning: thresholds.warnng: unknown key \"warnng\", did you mean \"warning\"?",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        let problems = validate(
+            r#"{"port": "5747", "timeout": -1, "retry_strategy": "sometimes", "thresholds": {"ok": 0.5, "warning": 0.9},
+                "services": [{"name": 5, "command": ["echo"], "args": "-v", "tags": [""], "exit_codes": {"one": 0.5}}]}"#,
+        );
+        assert!(problems.iter().all(|p| p.severity == Severity::Error));
+        let paths: Vec<&str> = problems.iter().map(|p| p.path.as_str()).collect();
+        for path in [
+            "port",
+            "timeout",
+            "retry_strategy",
+            "thresholds",
+            "services[0].name",
+            "services[0].command",
+            "services[0].args",
+            "services[0].tags[0]",
+            "services[0].exit_codes.one",
+        ] {
+            assert!(paths.contains(&path), "no problem with {} in {:?}", path, paths);
+        }
+        assert_eq!(problems.len(), 9);
+
+        let problems = validate("[]");
+        assert_eq!(messages(&problems), ["error: : settings must be an object (a mapping in YAML, a table in TOML)"]);
+    }
+
+    #[test]
+    fn rejects_missing_required_keys() {
+        let problems = validate(r#"{"services": [{"interval": 60}]}"#);
+        assert_eq!(
+            messages(&problems),
+            [
+                "error: services[0]: missing required key \"name\"",
+                "error: services[0]: missing required key \"command\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let problems = validate(
+            r#"{"services": [{"name": "web", "command": "true"}, {"name": "db", "command": "true"},
+                {"name": "web", "command": "false"}]}"#,
+        );
+        assert_eq!(
+            messages(&problems),
+            ["error: services[2].name: duplicate service name \"web\", already used by settings.json services[0]"]
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_names_across_files() {
+        let mut validation = Validation::default();
+        validate_file(&mut validation, "settings.json", r#"{"services": [{"name": "web", "command": "true"}]}"#);
+        validate_file(&mut validation, "web.json", r#"{"services": [{"name": "web", "command": "true"}]}"#);
+        assert_eq!(validation.problems.len(), 1);
+        assert_eq!(validation.problems[0].file, "web.json");
+        assert_eq!(
+            validation.problems[0].message,
+            "duplicate service name \"web\", already used by settings.json services[0]"
+        );
+    }
+
+    #[test]
+    fn points_problems_to_their_line_and_column() {
+        let json = "{\n  \"interval\": 60,\n  \"services\": [\n    {\"name\": \"web\", \"command\": \"true\", \"timeout\": 0},\n    {\n      \"name\": \"db\",\n      \"command\": \"true\",\n      \"retries\": 3\n    }\n  ]\n}";
+        let problems = validate(json);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].location, Some((4, 40)));
+        assert_eq!(
+            problems[0].to_string(),
+            "error: settings.json:4:40: services[0].timeout: must be a number of seconds above 0, got 0"
+        );
+        assert_eq!(problems[1].location, Some((8, 7)));
+        assert_eq!(problems[1].path, "services[1].retries");
+
+        // Problems with the whole file, or a path that isn't in the file, have no location
+        let problem = Problem::error("settings.json", "", None, "Unable to open the file".to_string());
+        assert_eq!(problem.to_string(), "error: settings.json: Unable to open the file");
+    }
+}
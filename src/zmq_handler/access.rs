@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::utils::interpolation::interpolate;
+
+/// The commands that can be given to roles, `*` allowing every command
+pub const COMMANDS: &[&str] = &[
+    "service", "list", "settings", "groups", "audit", "run", "pause", "resume", "report", "incidents", "incident", "ack",
+    "silence",
+];
+
+/// A client of the control socket, identified by its CURVE public key or a shared token
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AccessClient {
+    pub name: String,
+    /// The z85 encoded CURVE public key of the client
+    pub key: Option<String>,
+    /// A shared token sent along with the commands, never shown to clients
+    #[serde(skip)]
+    pub token: Option<String>,
+    pub role: String,
+}
+
+/// Role-based access control of the commands of the control socket
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AccessSettings {
+    /// The commands every role is allowed to use
+    pub roles: HashMap<String, Vec<String>>,
+    pub clients: Vec<AccessClient>,
+    /// The role of clients that don't identify themselves, denied if `None`
+    pub default_role: Option<String>,
+}
+
+impl AccessSettings {
+    /// Parses the `access` object of the settings. Tokens can use the same placeholders as the commands of the services.
+    pub fn from_value(value: &Value) -> Option<AccessSettings> {
+        let roles = value
+            .get("roles")?
+            .as_object()?
+            .iter()
+            .map(|(role, commands)| {
+                let commands = commands
+                    .as_array()
+                    .map(|arr| arr.iter().filter_map(|c| c.as_str().map(String::from)).collect())
+                    .unwrap_or_default();
+                (role.clone(), commands)
+            })
+            .collect();
+        let clients = value
+            .get("clients")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .enumerate()
+                    .filter_map(|(i, client)| {
+                        Some(AccessClient {
+                            name: client
+                                .get("name")
+                                .and_then(|v| v.as_str())
+                                .map(String::from)
+                                .unwrap_or(format!("client {}", i)),
+                            key: client.get("key").and_then(|v| v.as_str()).map(String::from),
+                            token: client
+                                .get("token")
+                                .and_then(|v| v.as_str())
+                                .and_then(|t| interpolate(t).ok()),
+                            role: client.get("role")?.as_str()?.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let default_role = value
+            .get("default_role")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        Some(AccessSettings {
+            roles,
+            clients,
+            default_role,
+        })
+    }
+
+    /// Checks whether a client may use a command.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The z85 encoded CURVE public key of the client, if authenticated with CURVE.
+    /// * `token` - The token sent along with the command, if any.
+    /// * `command` - The name of the command, see `COMMANDS`.
+    ///
+    /// # Returns
+    ///
+    /// The name of the client if allowed, otherwise the reason it was denied.
+    pub fn authorize(&self, user_id: Option<&str>, token: Option<&str>, command: &str) -> Result<String, String> {
+        let client = self.clients.iter().find(|client| {
+            let by_key = client.key.as_deref().is_some_and(|key| Some(key) == user_id);
+            let by_token = match (client.token.as_deref(), token) {
+                (Some(expected), Some(token)) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+                _ => false,
+            };
+            by_key || by_token
+        });
+
+        let (name, role) = match (client, &self.default_role) {
+            (Some(client), _) => (client.name.clone(), &client.role),
+            (None, _) if token.is_some() => return Err("Access denied: invalid token".to_string()),
+            (None, Some(default_role)) => ("anonymous client".to_string(), default_role),
+            (None, None) => return Err("Access denied: unknown client".to_string()),
+        };
+        let allowed = self
+            .roles
+            .get(role)
+            .is_some_and(|commands| commands.iter().any(|c| c == "*" || c == command));
+        if allowed {
+            Ok(name)
+        } else {
+            Err(format!("Access denied: {} (role {}) can't use the \"{}\" command", name, role, command))
+        }
+    }
+}
+
+/// Compares two byte strings without bailing out at the first difference, to not leak the token through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADMIN_KEY: &str = "rq:rM>}U?@Lns47E1%kR.o@n%FcmmsL/@{H8]yf7";
+
+    fn access_settings(default_role: Option<&str>) -> AccessSettings {
+        AccessSettings::from_value(&json!({
+            "roles": {
+                "admin": ["*"],
+                "operator": ["service", "list", "run", "ack"],
+                "viewer": ["service", "list"],
+                "nobody": []
+            },
+            "clients": [
+                {"name": "ops laptop", "key": ADMIN_KEY, "role": "admin"},
+                {"name": "ci", "token": "s3cret", "role": "operator"},
+                {"name": "dashboard", "token": "view-only", "role": "viewer"},
+                {"name": "ghost", "token": "ghost", "role": "missing"},
+                {"name": "no role", "token": "no-role"}
+            ],
+            "default_role": default_role
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn allows_the_commands_of_the_role() {
+        let access = access_settings(None);
+        assert_eq!(access.authorize(Some(ADMIN_KEY), None, "pause"), Ok("ops laptop".to_string()));
+        assert_eq!(access.authorize(Some(ADMIN_KEY), None, "silence"), Ok("ops laptop".to_string()));
+        assert_eq!(access.authorize(None, Some("s3cret"), "run"), Ok("ci".to_string()));
+        assert_eq!(access.authorize(None, Some("view-only"), "list"), Ok("dashboard".to_string()));
+    }
+
+    #[test]
+    fn denies_the_commands_outside_of_the_role() {
+        let access = access_settings(None);
+        assert_eq!(
+            access.authorize(None, Some("s3cret"), "pause"),
+            Err("Access denied: ci (role operator) can't use the \"pause\" command".to_string())
+        );
+        assert_eq!(
+            access.authorize(None, Some("view-only"), "ack"),
+            Err("Access denied: dashboard (role viewer) can't use the \"ack\" command".to_string())
+        );
+        // A role that isn't defined allows nothing
+        assert!(access.authorize(None, Some("ghost"), "list").is_err());
+    }
+
+    #[test]
+    fn denies_unknown_clients_without_a_default_role() {
+        let access = access_settings(None);
+        assert_eq!(access.authorize(None, None, "list"), Err("Access denied: unknown client".to_string()));
+        assert_eq!(
+            access.authorize(Some("some other key"), None, "list"),
+            Err("Access denied: unknown client".to_string())
+        );
+        // Clients without a role are left out
+        assert!(access.clients.iter().all(|client| client.name != "no role"));
+        assert_eq!(
+            access.authorize(None, Some("no-role"), "list"),
+            Err("Access denied: invalid token".to_string())
+        );
+    }
+
+    #[test]
+    fn gives_unknown_clients_the_default_role() {
+        let access = access_settings(Some("viewer"));
+        assert_eq!(access.authorize(None, None, "list"), Ok("anonymous client".to_string()));
+        assert_eq!(
+            access.authorize(None, None, "run"),
+            Err("Access denied: anonymous client (role viewer) can't use the \"run\" command".to_string())
+        );
+        let nobody = access_settings(Some("nobody"));
+        assert!(nobody.authorize(None, None, "list").is_err());
+    }
+
+    #[test]
+    fn rejects_a_wrong_token_even_with_a_default_role() {
+        let access = access_settings(Some("viewer"));
+        assert_eq!(access.authorize(None, Some("s3cre"), "list"), Err("Access denied: invalid token".to_string()));
+        assert_eq!(access.authorize(None, Some(""), "list"), Err("Access denied: invalid token".to_string()));
+    }
+
+    #[test]
+    fn compares_tokens_in_full() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cres"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cret "));
+        assert!(!constant_time_eq(b"", b"s3cret"));
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+
+/// What came of a request to the control socket
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// The command was executed
+    Ok,
+    /// The client isn't allowed to use the command
+    Denied,
+    /// The command couldn't be parsed
+    Invalid,
+}
+
+impl Outcome {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "ok" => Some(Outcome::Ok),
+            "denied" => Some(Outcome::Denied),
+            "invalid" => Some(Outcome::Invalid),
+            _ => None,
+        }
+    }
+}
+
+/// A single line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// The name of the client from the `access` settings, its CURVE public key, or `anonymous client`
+    pub client: String,
+    /// The address the request came from, if known
+    pub address: Option<String>,
+    pub command: String,
+    pub args: Vec<String>,
+    pub outcome: Outcome,
+    /// Why the request was denied or invalid
+    pub reason: Option<String>,
+}
+
+/// Appends an entry to the audit log, creating it (only readable by the owner) if it doesn't exist yet.
+///
+/// # Arguments
+///
+/// * `path` - The path of the audit log.
+/// * `entry` - The entry to append as a line of JSON.
+pub fn append(path: &str, entry: &AuditEntry) -> io::Result<()> {
+    let mut line = serde_json::to_string(entry).map_err(io::Error::other)?;
+    line.push('\n');
+    // A single write, so concurrent writers can't interleave within a line
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Reads the entries of the audit log between two points in time.
+///
+/// # Arguments
+///
+/// * `path` - The path of the audit log.
+/// * `since` - Leaves out entries before this time.
+/// * `until` - Leaves out entries after this time.
+///
+/// # Returns
+///
+/// The entries in the order they were logged. Lines that can't be parsed are skipped.
+pub fn read(path: &str, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> io::Result<Vec<AuditEntry>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut entries = vec![];
+    for line in BufReader::new(file).lines() {
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else {
+            continue;
+        };
+        if since.is_some_and(|since| entry.timestamp < since) || until.is_some_and(|until| entry.timestamp > until) {
+            continue;
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use zmq::{CurveKeyPair, Socket};
+
+use crate::utils::interpolation::interpolate;
+
+/// The ZAP domain of the control socket
+pub const ZAP_DOMAIN: &str = "status";
+
+/// CURVE authentication and encryption of the control socket
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CurveSettings {
+    /// The z85 encoded secret key of the server, never shown to clients
+    #[serde(skip)]
+    pub secret_key: String,
+    /// The z85 encoded public keys of the clients allowed to connect, any client knowing the server key if empty
+    pub allowed_clients: Vec<String>,
+}
+
+impl CurveSettings {
+    /// Parses the `curve` object of the settings, e.g. `{"secret_key": "${file:keys/server.key}", "allowed_clients": [...]}`.
+    /// The secret key can use the same placeholders as the commands of the services.
+    pub fn from_value(value: &Value) -> Option<CurveSettings> {
+        let secret_key = interpolate(value.get("secret_key")?.as_str()?).ok()?;
+        let allowed_clients = value
+            .get("allowed_clients")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|k| k.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        Some(CurveSettings {
+            secret_key: secret_key.trim().to_string(),
+            allowed_clients,
+        })
+    }
+}
+
+/// Whether the string is a z85 encoded CURVE key
+pub fn is_curve_key(key: &str) -> bool {
+    key.len() == 40 && zmq::z85_decode(key).is_ok_and(|k| k.len() == 32)
+}
+
+/// Whether libzmq was built with CURVE support
+pub fn curve_supported() -> bool {
+    zmq::has("curve").unwrap_or(false)
+}
+
+/// Answers the ZAP (ZeroMQ Authentication Protocol) requests of the control socket, see https://rfc.zeromq.org/spec/27/
+///
+/// Only CURVE clients are let through, and only the `allowed_clients` if there are any.
+/// The z85 encoded public key of the client becomes its User-Id.
+///
+/// # Arguments
+///
+/// * `socket` - A REP socket bound to `inproc://zeromq.zap.01`.
+/// * `allowed_clients` - The z85 encoded public keys of the allowed clients.
+pub fn zap_handler(socket: Socket, allowed_clients: Vec<String>) {
+    loop {
+        let request = match socket.recv_multipart(0) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Failed to receive ZAP request: {}", e);
+                continue;
+            }
+        };
+        // version, request id, domain, address, identity, mechanism, credentials...
+        if request.len() < 6 {
+            eprintln!("Received malformed ZAP request");
+            continue;
+        }
+        let address = String::from_utf8_lossy(&request[3]).to_string();
+        let client_key = match (request[5].as_slice(), request.get(6)) {
+            (b"CURVE", Some(key)) => zmq::z85_encode(key).ok(),
+            _ => None,
+        };
+
+        let (status_code, status_text, user_id) = match client_key {
+            Some(key) if allowed_clients.is_empty() || allowed_clients.contains(&key) => ("200", "OK", key),
+            Some(key) => {
+                eprintln!("!  Denied client {} from {}", key, address);
+                ("400", "Client key not allowed", String::new())
+            }
+            None => {
+                eprintln!("!  Denied client without CURVE from {}", address);
+                ("400", "CURVE is required", String::new())
+            }
+        };
+
+        let reply: Vec<Vec<u8>> = vec![
+            request[0].clone(),
+            request[1].clone(),
+            status_code.as_bytes().to_vec(),
+            status_text.as_bytes().to_vec(),
+            user_id.into_bytes(),
+            vec![],
+        ];
+        if let Err(e) = socket.send_multipart(reply, 0) {
+            eprintln!("Failed to send ZAP reply: {}", e);
+        }
+    }
+}
+
+/// The CURVE keys a client connects to the control socket with, decoded from z85
+pub struct ClientKeys {
+    pub server_public: Vec<u8>,
+    pub public: Vec<u8>,
+    pub secret: Vec<u8>,
+}
+
+impl ClientKeys {
+    /// Loads the keys of a client from the files written by `status keygen --output`.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_key` - The file with the public key of the server, e.g. `keys/server.pub`.
+    /// * `client_key` - The prefix of the `.key` and `.pub` files of the client, e.g. `keys/dashboard`.
+    ///
+    /// # Returns
+    ///
+    /// The keys, or which file couldn't be read.
+    pub fn load(server_key: &str, client_key: &str) -> Result<ClientKeys, String> {
+        Ok(ClientKeys {
+            server_public: read_key(server_key)?,
+            public: read_key(&format!("{}.pub", client_key))?,
+            secret: read_key(&format!("{}.key", client_key))?,
+        })
+    }
+
+    /// Makes the socket use CURVE with these keys, has to be done before it connects
+    pub fn apply(&self, socket: &Socket) -> zmq::Result<()> {
+        socket.set_curve_serverkey(&self.server_public)?;
+        socket.set_curve_publickey(&self.public)?;
+        socket.set_curve_secretkey(&self.secret)
+    }
+}
+
+/// Reads a z85 encoded CURVE key from a file
+fn read_key(path: &str) -> Result<Vec<u8>, String> {
+    let key = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    zmq::z85_decode(key.trim()).map_err(|_| format!("{} isn't a z85 encoded CURVE key", path))
+}
+
+/// Generates a CURVE key pair, for the server or a client.
+///
+/// # Arguments
+///
+/// * `output` - Writes the keys to `<output>.key` (secret, only readable by the owner) and `<output>.pub`,
+///   prints them if left out.
+pub fn generate_keys(output: Option<String>) -> io::Result<()> {
+    if !curve_supported() {
+        return Err(io::Error::other("libzmq was built without CURVE support"));
+    }
+    let pair = CurveKeyPair::new().map_err(io::Error::other)?;
+    let encode = |key: &[u8]| zmq::z85_encode(key).map_err(|e| io::Error::other(format!("{:?}", e)));
+    let public_key = encode(&pair.public_key)?;
+    let secret_key = encode(&pair.secret_key)?;
+
+    match output {
+        Some(output) => {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(format!("{}.key", output))?
+                .write_all(format!("{}\n", secret_key).as_bytes())?;
+            fs::write(format!("{}.pub", output), format!("{}\n", public_key))?;
+            println!("Wrote {}.key and {}.pub", output, output);
+        }
+        None => {
+            println!("public: {}", public_key);
+            println!("secret: {}", secret_key);
+        }
+    }
+    Ok(())
+}
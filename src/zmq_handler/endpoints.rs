@@ -0,0 +1,100 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process;
+use std::sync::Once;
+use std::thread;
+
+/// The file path of an IPC endpoint, e.g. `/run/status.sock` for `ipc:///run/status.sock`
+pub fn ipc_path(endpoint: &str) -> Option<&str> {
+    endpoint.strip_prefix("ipc://").filter(|path| !path.is_empty() && *path != "*")
+}
+
+/// Removes an IPC socket file left behind by a daemon that didn't exit cleanly.
+///
+/// Fails if another daemon is still listening on it, or if the path is something else than a socket.
+pub fn remove_stale_socket(path: &str) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and isn't a socket", path)));
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use by another process", path)));
+    }
+    fs::remove_file(path)
+}
+
+/// Sets the file permissions of an IPC socket, e.g. `0o660` to only let a group connect.
+///
+/// Done after binding rather than through the umask, as that is shared with the rest of the process,
+/// e.g. the test loops writing files and starting commands while a restarted listener binds.
+pub fn set_socket_permissions(path: &str, mode: u32) -> io::Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+/// The IPC socket files of the bound endpoints, removed when this is dropped,
+/// e.g. when the listener stops, panics or fails to bind the next endpoint.
+#[derive(Default)]
+pub struct SocketFiles {
+    paths: Vec<String>,
+}
+
+impl SocketFiles {
+    /// Takes care of the socket file of an endpoint that was just bound
+    pub fn push(&mut self, path: &str) {
+        self.paths.push(path.to_string());
+    }
+
+    /// Also removes the socket files when the daemon is asked to stop, as exiting doesn't drop them.
+    /// Only listens for the signals once, a restarted listener binds the same endpoints again.
+    pub fn remove_on_exit(&self) {
+        static LISTENING: Once = Once::new();
+        if self.paths.is_empty() {
+            return;
+        }
+        let paths = self.paths.clone();
+        LISTENING.call_once(|| {
+            let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+                Ok(signals) => signals,
+                Err(e) => {
+                    eprintln!("Unable to listen for signals, IPC sockets won't be removed on exit: {}", e);
+                    return;
+                }
+            };
+            thread::Builder::new()
+                .name("Signals".to_string())
+                .spawn(move || {
+                    if let Some(signal) = signals.forever().next() {
+                        remove_sockets(&paths);
+                        process::exit(128 + signal);
+                    }
+                })
+                .unwrap();
+        });
+    }
+}
+
+impl Drop for SocketFiles {
+    fn drop(&mut self) {
+        remove_sockets(&self.paths);
+    }
+}
+
+/// Removes the IPC socket files that are still there
+fn remove_sockets(paths: &[String]) {
+    for path in paths {
+        if Path::new(path).exists()
+            && let Err(e) = fs::remove_file(path)
+        {
+            eprintln!("Unable to remove {}: {}", path, e);
+        }
+    }
+}
@@ -1,8 +1,19 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use clap::error::ErrorKind;
 use clap::{Args, Parser, Subcommand};
 use serde_json;
+use std::sync::PoisonError;
 
+use crate::acknowledgement::{Acknowledgement, Silence};
+use crate::history::{self, HistorySettings};
+use crate::incidents::Incident;
+use crate::report::{self, Report};
 use crate::service::Service;
-use crate::settings::ResultOutput;
+use crate::settings::{GroupSummary, ResultOutput};
+use crate::utils::partial_success::PartialSuccess;
+use crate::utils::time::{parse_deadline, parse_duration, parse_time};
+use crate::zmq_handler::audit::{self, AuditEntry, Outcome};
 use crate::zmq_handler::ZmqHandler;
 
 /// Status daemon written in rust.
@@ -24,14 +35,278 @@ enum Commands {
 
     /// Show settings
     Settings,
+
+    /// Shows the combined success score and status of every group of services
+    Groups(GroupsArgs),
+
+    /// Shows the requests made to the control socket, from the audit log
+    Audit(AuditArgs),
+
+    /// Runs the service(s) right away, without waiting for their interval
+    Run(Target),
+
+    /// Pauses the service(s), so they only run when explicitly asked to
+    Pause(Target),
+
+    /// Resumes the paused service(s)
+    Resume(Target),
+
+    /// Acknowledges the failing service(s), until they are ok again
+    Ack(AckArgs),
+
+    /// Silences the service(s) for a while, whether they are failing or not
+    Silence(SilenceArgs),
+
+    /// Shows the uptime, outages and availability of the tests of the service(s) over a period, from the run history
+    Report(ReportArgs),
+
+    /// Shows the incidents of the services, from the first critical run until they recovered
+    Incidents(IncidentsArgs),
+
+    /// Shows a single incident, or attaches a note to it
+    Incident(IncidentArgs),
 }
 
+impl Commands {
+    /// Whether the command reads the service from the settings, the commands changing services lock them themselves
+    fn reads(&self, service: &Service) -> bool {
+        match self {
+            Commands::Service(args) => args.selector.matches(service),
+            Commands::Report(args) => args.selector.matches(service),
+            Commands::List | Commands::Settings | Commands::Groups(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the command shows the raw output of the services
+    fn reads_raw_output(&self) -> bool {
+        matches!(self, Commands::Service(args) if args.raw)
+    }
+
+    /// The name of the command, as used by the roles in the `access` settings
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Service(_) => "service",
+            Commands::List => "list",
+            Commands::Settings => "settings",
+            Commands::Groups(_) => "groups",
+            Commands::Audit(_) => "audit",
+            Commands::Run(_) => "run",
+            Commands::Pause(_) => "pause",
+            Commands::Resume(_) => "resume",
+            Commands::Ack(_) => "ack",
+            Commands::Silence(_) => "silence",
+            Commands::Report(_) => "report",
+            Commands::Incidents(_) => "incidents",
+            Commands::Incident(_) => "incident",
+        }
+    }
+}
+
+/// Selects services by name, tags and group
 #[derive(Args)]
-struct ServiceArgs {
+struct Selector {
     /// The name of the service to show information from
     #[arg(default_value = "all")]
     names: Option<Vec<String>>,
 
+    /// Only services with this tag, can be repeated to require multiple tags
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+
+    /// Only services in this group
+    #[arg(long = "group")]
+    group: Option<String>,
+}
+
+impl Selector {
+    /// Whether the service is selected by the names, tags and group
+    fn matches(&self, service: &Service) -> bool {
+        selects(self.names.as_deref().unwrap_or_default(), &self.tags, self.group.as_ref(), service)
+    }
+}
+
+/// Selects the services a command changes, by name, tags and group.
+/// Unlike `Selector` nothing is selected by default, `all` has to be given to change every service.
+#[derive(Args)]
+struct Target {
+    /// The names of the services to change, or all
+    #[arg(required_unless_present_any = ["tags", "group"])]
+    names: Vec<String>,
+
+    /// Only services with this tag, can be repeated to require multiple tags
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+
+    /// Only services in this group
+    #[arg(long = "group")]
+    group: Option<String>,
+}
+
+impl Target {
+    /// Whether the service is selected by the names, tags and group
+    fn matches(&self, service: &Service) -> bool {
+        selects(&self.names, &self.tags, self.group.as_ref(), service)
+    }
+}
+
+/// Whether a service has one of the names (if any, and not `all`), all the tags and the group (if any)
+fn selects(names: &[String], tags: &[String], group: Option<&String>, service: &Service) -> bool {
+    let by_name = names.is_empty() || names[0] == "all" || names.contains(&service.name);
+    let by_tags = tags.iter().all(|tag| service.tags.contains(tag));
+    let by_group = group.is_none_or(|group| service.group.as_ref() == Some(group));
+    by_name && by_tags && by_group
+}
+
+#[derive(Args)]
+struct GroupsArgs {
+    /// The groups to show, all groups if left out
+    groups: Vec<String>,
+}
+
+#[derive(Args)]
+struct AuditArgs {
+    /// Only requests from this time on, e.g. 2024-05-01T12:00:00Z, 2024-05-01, a unix timestamp or 30m/12h/7d ago
+    #[arg(long = "since", value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Only requests up to this time, in the same formats as --since
+    #[arg(long = "until", value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// Only requests from this client
+    #[arg(long = "client")]
+    client: Option<String>,
+
+    /// Only requests with this outcome: ok, denied or invalid
+    #[arg(long = "outcome", value_parser = parse_outcome)]
+    outcome: Option<Outcome>,
+
+    /// Only the most recent requests, 0 for all of them
+    #[arg(long = "limit", default_value_t = 100)]
+    limit: usize,
+}
+
+fn parse_outcome(s: &str) -> Result<Outcome, String> {
+    Outcome::from_str(s).ok_or(format!("invalid outcome \"{}\", expected ok, denied or invalid", s))
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    #[command(flatten)]
+    selector: Selector,
+
+    /// The start of the period, e.g. 2024-05-01, 2024-05-01T12:00:00Z, a unix timestamp or 30d ago. Defaults to 30 days ago
+    #[arg(long = "from", value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+
+    /// The end of the period, in the same formats as --from. Defaults to now
+    #[arg(long = "to", value_parser = parse_time)]
+    to: Option<DateTime<Utc>>,
+
+    /// How runs that only partly succeeded count: weighted, up or down. Defaults to the partial_success of each service
+    #[arg(long = "partial", value_parser = parse_partial_success)]
+    partial: Option<PartialSuccess>,
+
+    /// Output format, json or csv
+    #[arg(long = "format", default_value = "json", value_parser = ["json", "csv"])]
+    format: String,
+}
+
+fn parse_partial_success(s: &str) -> Result<PartialSuccess, String> {
+    PartialSuccess::from_str(s).ok_or(format!("invalid rule \"{}\", expected weighted, up or down", s))
+}
+
+#[derive(Args)]
+struct AckArgs {
+    #[command(flatten)]
+    target: Target,
+
+    /// What is being done about it
+    #[arg(long = "comment", num_args = 1..)]
+    comment: Vec<String>,
+
+    /// Clear the acknowledgement at this time even if still failing, e.g. 2024-05-01T12:00:00Z, a unix timestamp or 4h from now
+    #[arg(long = "until", value_parser = parse_deadline)]
+    until: Option<DateTime<Utc>>,
+
+    /// Clear the acknowledgement right away instead
+    #[arg(long = "clear", conflicts_with_all = ["comment", "until"])]
+    clear: bool,
+}
+
+#[derive(Args)]
+struct SilenceArgs {
+    #[command(flatten)]
+    target: Target,
+
+    /// How long to silence the service(s) for, e.g. 30m, 4h or 2d
+    #[arg(long = "for", value_parser = parse_duration, required_unless_present = "clear")]
+    duration: Option<Duration>,
+
+    /// Why the service(s) are silenced
+    #[arg(long = "comment", num_args = 1..)]
+    comment: Vec<String>,
+
+    /// Clear the silence right away instead
+    #[arg(long = "clear", conflicts_with_all = ["duration", "comment"])]
+    clear: bool,
+}
+
+#[derive(Args)]
+struct IncidentsArgs {
+    /// Only the incidents that are still open
+    #[arg(long = "open")]
+    open: bool,
+
+    /// Only the incidents of this service
+    #[arg(long = "service")]
+    service: Option<String>,
+
+    /// Only the most recent incidents, 0 for all of them
+    #[arg(long = "limit", default_value_t = 50)]
+    limit: usize,
+}
+
+#[derive(Args)]
+struct IncidentArgs {
+    #[command(subcommand)]
+    command: IncidentCommands,
+}
+
+#[derive(Subcommand)]
+enum IncidentCommands {
+    /// Shows an incident along with its notes
+    Show {
+        id: u64,
+    },
+
+    /// Attaches a note to an incident, e.g. what is being done about it
+    Note {
+        id: u64,
+
+        /// The text of the note
+        #[arg(required = true, num_args = 1..)]
+        text: Vec<String>,
+    },
+}
+
+/// A request received on the control socket
+struct Request {
+    content: String,
+    /// The CURVE public key of the client, if authenticated with CURVE
+    user_id: Option<String>,
+    /// The token sent along with the command, if any
+    token: Option<String>,
+    /// The address the request came from, if known
+    address: Option<String>,
+}
+
+#[derive(Args)]
+struct ServiceArgs {
+    #[command(flatten)]
+    selector: Selector,
+
     /// Shorten the result
     #[arg(long = "short")]
     short: bool,
@@ -39,6 +314,14 @@ struct ServiceArgs {
     /// Only show errors
     #[arg(long = "errors")]
     errors: bool,
+
+    /// Show the raw stdout/stderr captured from the last run instead of the parsed result
+    #[arg(long = "raw")]
+    raw: bool,
+
+    /// Leave out acknowledged and silenced services
+    #[arg(long = "unhandled")]
+    unhandled: bool,
 }
 
 impl ZmqHandler {
@@ -46,73 +329,235 @@ impl ZmqHandler {
     /// It runs in an infinite loop.
     pub fn listen(&mut self) {
         loop {
-            // Receive a multipart message from the subscriber
-            let msg_result = self.router.recv_multipart(0);
-
-            match msg_result {
-                Ok(msg) => {
-                    let (id, input) = (msg[0].clone(), String::from_utf8(msg[1].clone()));
-                    match input {
-                        Ok(input_str) => {
-                            // Now that all that is done, we can finally look at what the input actually is
-                            println!("> {}", input_str);
-
-                            // Pass the input to the parser and get the reply
-                            let reply = self.parser(input_str);
-
-                            // Send reply back to client
-                            self.router
-                                .send_multipart(&[id, reply.into_bytes()], 0)
-                                .unwrap();
-                        }
-                        Err(_) => eprintln!("Received invalid UTF-8 data"),
+            let (id, mut message) = match self.receive() {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("Failed to receive message: {}", e);
+                    continue;
+                }
+            };
+            // Set by the ZAP handler to the public key of CURVE clients
+            let user_id = message
+                .gets("User-Id")
+                .filter(|u| !u.is_empty())
+                .map(String::from);
+            let address = message.gets("Peer-Address").map(String::from);
+
+            match String::from_utf8(message.to_vec()) {
+                Ok(input_str) => {
+                    // Commands can be sent as plain text, or as JSON along with a token: {"command": "service", "token": "..."}
+                    let (content, token) = match serde_json::from_str::<serde_json::Value>(&input_str) {
+                        Ok(request) if request.get("command").is_some_and(|c| c.is_string()) => (
+                            request["command"].as_str().unwrap_or_default().to_string(),
+                            request.get("token").and_then(|t| t.as_str()).map(String::from),
+                        ),
+                        _ => (input_str, None),
+                    };
+
+                    // Now that all that is done, we can finally look at what the input actually is
+                    println!("> {}", content);
+
+                    // Pass the input to the parser and get the reply
+                    let reply = self.parser(Request {
+                        content,
+                        user_id,
+                        token,
+                        address,
+                    });
+
+                    // Send reply back to client, it might have gone away in the meantime
+                    if let Err(e) = self.router.send_multipart(&[id, reply.into_bytes()], 0) {
+                        eprintln!("Failed to send reply: {}", e);
                     }
                 }
-                Err(e) => eprintln!("Failed to receive message: {}", e),
+                Err(_) => eprintln!("Received invalid UTF-8 data"),
             }
         }
     }
 
-    /// Parses the input and executes the appropriate commands.
+    /// Receives a message from a client, returning the identity of the client and the content of the message.
+    /// Any extra frames are ignored.
+    fn receive(&self) -> zmq::Result<(Vec<u8>, zmq::Message)> {
+        let id = self.router.recv_msg(0)?;
+        let message = if id.get_more() {
+            self.router.recv_msg(0)?
+        } else {
+            zmq::Message::new()
+        };
+        let mut more = message.get_more();
+        while more {
+            more = self.router.recv_msg(0)?.get_more();
+        }
+        Ok((id.to_vec(), message))
+    }
+
+    /// Parses the input and executes the appropriate commands, recording the request in the audit log if enabled.
     ///
     /// # Arguments
     ///
-    /// * `content` - A string that represents the input to be parsed.
-    fn parser(&mut self, content: String) -> String {
-        // Getting the settings
-        let settings = {
-            let locked_settings = self.settings.lock().unwrap();
-            (*locked_settings).clone()
+    /// * `request` - The request to be parsed, along with who sent it.
+    fn parser(&mut self, request: Request) -> String {
+        let audit_log = self.settings.lock().unwrap_or_else(PoisonError::into_inner).audit_log.clone();
+
+        let words: Vec<String> = request.content.split_whitespace().map(String::from).collect();
+        let mut entry = AuditEntry {
+            timestamp: Utc::now(),
+            client: request.user_id.clone().unwrap_or("anonymous client".to_string()),
+            address: request.address.clone(),
+            command: words.first().cloned().unwrap_or_default(),
+            args: words.iter().skip(1).cloned().collect(),
+            outcome: Outcome::Ok,
+            reason: None,
         };
+        let reply = self.execute(&words, &request, &mut entry);
+
+        if let Some(path) = audit_log
+            && let Err(e) = audit::append(&path, &entry)
+        {
+            eprintln!("Unable to write to the audit log {}: {}", path, e);
+        }
+        reply
+    }
 
-        let opts = match Cli::try_parse_from(
-            format!(
-                "{} {}",
-                std::env::current_exe()
-                    .unwrap_or_else(|_| std::path::PathBuf::from("status"))
-                    .display()
-                    .to_string(),
-                content
-            )
-            .to_string()
-            .split_whitespace()
-            .map(String::from)
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<String>>(),
-        ) {
+    /// Executes a command, filling in the client and outcome of the audit entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The words of the command.
+    /// * `request` - The request the command came from.
+    /// * `entry` - The audit entry of the request.
+    fn execute(&mut self, words: &[String], request: &Request, entry: &mut AuditEntry) -> String {
+        let executable = std::env::current_exe()
+            .unwrap_or_else(|_| std::path::PathBuf::from("status"))
+            .display()
+            .to_string();
+        let opts = match Cli::try_parse_from(std::iter::once(&executable).chain(words)) {
             // Was able to parse it
             Ok(v) => v,
 
             // Wants to show help menu, version menu, or just general error
             Err(e) => {
+                if !matches!(
+                    e.kind(),
+                    ErrorKind::DisplayHelp | ErrorKind::DisplayVersion | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+                ) {
+                    entry.outcome = Outcome::Invalid;
+                    entry.reason = e.to_string().lines().next().map(|l| l.trim_start_matches("error: ").to_string());
+                }
                 return e.to_string();
             }
         };
+        entry.command = opts.command.name().to_string();
+
+        // Getting the settings with only the services the command reads, without any secrets interpolated into them
+        let settings = self
+            .settings
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .snapshot(|service| opts.command.reads(service), opts.command.reads_raw_output());
+
+        // Checking whether the client may use the command at all
+        if let Some(access) = &settings.access {
+            match access.authorize(request.user_id.as_deref(), request.token.as_deref(), opts.command.name()) {
+                Ok(client) => entry.client = client,
+                Err(denied) => {
+                    eprintln!("!  {} ({})", denied, request.user_id.as_deref().unwrap_or("no CURVE key"));
+                    entry.outcome = Outcome::Denied;
+                    entry.reason = Some(denied.clone());
+                    return denied;
+                }
+            }
+        }
 
         match opts.command {
             Commands::Service(args) => self.service_handler(args, settings.services),
             Commands::Settings => format!("{}", settings),
+            Commands::Groups(args) => {
+                let groups: Vec<GroupSummary> = settings
+                    .groups()
+                    .into_iter()
+                    .filter(|g| args.groups.is_empty() || args.groups.contains(&g.group))
+                    .collect();
+                if groups.is_empty() {
+                    return "No groups found".to_string();
+                }
+                serde_json::to_string_pretty(&groups).unwrap_or("Failed to parse as JSON".to_string())
+            }
+            Commands::Audit(args) => match &settings.audit_log {
+                Some(path) => audit_handler(args, path),
+                None => "The audit log is disabled, set \"audit_log\" in the settings to enable it".to_string(),
+            },
+            Commands::Report(args) => match &settings.history {
+                Some(history) => report_handler(args, history, &settings.services),
+                None => "The run history is disabled, set \"history\" in the settings to enable reports".to_string(),
+            },
+            Commands::Incidents(args) => {
+                let incidents = self.incidents.lock().unwrap_or_else(PoisonError::into_inner);
+                incidents_handler(args, &incidents.incidents)
+            }
+            Commands::Incident(args) => {
+                let mut incidents = self.incidents.lock().unwrap_or_else(PoisonError::into_inner);
+                match args.command {
+                    IncidentCommands::Show { id } => match incidents.incidents.iter().find(|i| i.id == id) {
+                        Some(incident) => serde_json::to_string_pretty(incident).unwrap_or("Failed to parse as JSON".to_string()),
+                        None => format!("No incident #{}", id),
+                    },
+                    IncidentCommands::Note { id, text } => incidents.note(id, &entry.client, text.join(" ")).unwrap_or_else(|e| e),
+                }
+            }
+            Commands::Run(target) => self.control_handler(&target, |service| {
+                if service.next_run.is_none() && !service.paused {
+                    return format!("{} is already running", service.name);
+                }
+                service.run_requested = true;
+                format!("Running {}", service.name)
+            }),
+            Commands::Pause(target) => self.control_handler(&target, |service| {
+                service.paused = true;
+                format!("Paused {}", service.name)
+            }),
+            Commands::Resume(target) => self.control_handler(&target, |service| {
+                service.paused = false;
+                format!("Resumed {}", service.name)
+            }),
+            Commands::Ack(args) => {
+                let comment = (!args.comment.is_empty()).then(|| args.comment.join(" "));
+                self.control_handler(&args.target, |service| {
+                    if args.clear {
+                        service.acknowledged = None;
+                        return format!("Cleared the acknowledgement of {}", service.name);
+                    }
+                    if !service.status.is_problem() {
+                        return format!("{} isn't failing, nothing to acknowledge", service.name);
+                    }
+                    service.acknowledged = Some(Acknowledgement {
+                        by: entry.client.clone(),
+                        at: Utc::now(),
+                        comment: comment.clone(),
+                        until: args.until,
+                    });
+                    format!("Acknowledged {}", service.name)
+                })
+            }
+            Commands::Silence(args) => {
+                let comment = (!args.comment.is_empty()).then(|| args.comment.join(" "));
+                self.control_handler(&args.target, |service| match args.duration {
+                    Some(duration) if !args.clear => {
+                        let until = Utc::now() + duration;
+                        service.silenced = Some(Silence {
+                            by: entry.client.clone(),
+                            at: Utc::now(),
+                            comment: comment.clone(),
+                            until,
+                        });
+                        format!("Silenced {} until {}", service.name, until.to_rfc3339_opts(SecondsFormat::Secs, true))
+                    }
+                    _ => {
+                        service.silenced = None;
+                        format!("Cleared the silence of {}", service.name)
+                    }
+                })
+            }
             Commands::List => settings
                 .services
                 .iter()
@@ -123,10 +568,31 @@ impl ZmqHandler {
         }
     }
 
+    /// Handles the commands changing the services, i.e. "run", "pause", "resume", "ack" and "silence".
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Selects the services to change.
+    /// * `change` - Changes a single service, returning what was done.
+    fn control_handler(&mut self, target: &Target, change: impl Fn(&mut Service) -> String) -> String {
+        let mut locked_settings = self.settings.lock().unwrap_or_else(PoisonError::into_inner);
+        let changed: Vec<String> = locked_settings
+            .services
+            .iter_mut()
+            .filter(|service| target.matches(service))
+            .map(change)
+            .collect();
+        if changed.is_empty() {
+            return "No services found".to_string();
+        }
+        self.wakeup.notify_all();
+        changed.join("\n")
+    }
+
     /// Handles the "service" command.
     ///
     /// This function will print the details of the specified services in a JSON format.
-    /// If no services are specified, it will print the details of all services.
+    /// If no services are specified, it will print the details of all services, optionally filtered by tags and group.
     ///
     /// # Arguments
     ///
@@ -135,35 +601,41 @@ impl ZmqHandler {
     fn service_handler(&mut self, args: ServiceArgs, services: Vec<Service>) -> String {
         let short = args.short;
         let errors = args.errors;
-        let names = args.names.as_ref();
-
-        // Check if names are specified
-        let mut services_to_print = match names {
-            Some(names) if !names.is_empty() && names[0] != "all" => services
-                .into_iter()
-                .filter(|service| names.contains(&service.name))
-                .collect(),
-            _ => services,
-        };
+
+        let mut services_to_print: Vec<Service> = services
+            .into_iter()
+            .filter(|service| args.selector.matches(service))
+            .collect();
 
         if services_to_print.is_empty() {
             return "No services found".to_string();
         }
 
+        if args.unhandled {
+            services_to_print.retain(|service| !service.is_handled());
+        }
         if errors {
-            services_to_print.retain(|service| service.successes != 1.0);
+            services_to_print.retain(|service| service.status.is_problem());
             for s in &mut services_to_print {
-                match &mut s.result {
-                    ResultOutput::Result(r) => {
-                        r.retain(|test_result| test_result.success != 1.0);
-                    }
-                    _ => {}
+                if let ResultOutput::Result(r) = &mut s.result {
+                    r.retain(|test_result| test_result.status.is_some_and(|status| status.is_problem()));
                 }
             }
         }
 
         // Prepare the output
-        let output = if short {
+        let output = if args.raw {
+            let raw_services: Vec<_> = services_to_print
+                .iter()
+                .map(|s: &Service| {
+                    serde_json::json!({
+                        "name": s.name,
+                        "raw": s.raw_output.as_deref(),
+                    })
+                })
+                .collect();
+            serde_json::to_string_pretty(&raw_services)
+        } else if short {
             // If `short` option is specified, manually construct JSON excluding the `result` field
             let short_services: Vec<_> = services_to_print
                 .iter()
@@ -171,13 +643,20 @@ impl ZmqHandler {
                     let timestamp: Option<i64> = s.last_run.map(|t| t.timestamp());
                     serde_json::json!({
                         "name": s.name,
+                        "tags": s.tags,
+                        "group": s.group,
                         "command": s.command,
                         "args": s.args,
                         "interval": s.interval,
                         "timeout": s.timeout,
                         "successes": s.successes,
+                        "status": s.status,
                         "pause_on_no_internet": s.pause_on_no_internet,
                         "last_run": timestamp,
+                        "next_run": s.next_run.map(|t| t.timestamp()),
+                        "paused": s.paused,
+                        "acknowledged": s.acknowledged,
+                        "silenced": s.silenced,
                     })
                 })
                 .collect();
@@ -189,3 +668,79 @@ impl ZmqHandler {
         output.unwrap_or("Failed to parse as JSON".to_string())
     }
 }
+
+/// Handles the "audit" command, showing the matching entries of the audit log in a JSON format.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the "audit" command.
+/// * `path` - The path of the audit log.
+fn audit_handler(args: AuditArgs, path: &str) -> String {
+    let mut entries = match audit::read(path, args.since, args.until) {
+        Ok(entries) => entries,
+        Err(e) => return format!("Unable to read the audit log: {}", e),
+    };
+    entries.retain(|entry| {
+        args.client.as_ref().is_none_or(|client| entry.client == *client)
+            && args.outcome.is_none_or(|outcome| entry.outcome == outcome)
+    });
+    if args.limit > 0 && entries.len() > args.limit {
+        entries.drain(..entries.len() - args.limit);
+    }
+    if entries.is_empty() {
+        return "No audit entries found".to_string();
+    }
+    serde_json::to_string_pretty(&entries).unwrap_or("Failed to parse as JSON".to_string())
+}
+
+/// Handles the "incidents" command, listing the incidents oldest first.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the "incidents" command.
+/// * `incidents` - Every incident.
+fn incidents_handler(args: IncidentsArgs, incidents: &[Incident]) -> String {
+    let mut incidents: Vec<&Incident> = incidents
+        .iter()
+        .filter(|incident| !args.open || incident.is_open())
+        .filter(|incident| args.service.as_ref().is_none_or(|service| incident.service == *service))
+        .collect();
+    if args.limit > 0 && incidents.len() > args.limit {
+        incidents.drain(..incidents.len() - args.limit);
+    }
+    if incidents.is_empty() {
+        return "No incidents found".to_string();
+    }
+    serde_json::to_string_pretty(&incidents).unwrap_or("Failed to parse as JSON".to_string())
+}
+
+/// Handles the "report" command, computing the uptime of the selected services over a period from the run history.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the "report" command.
+/// * `history` - The history settings.
+/// * `services` - The available services.
+fn report_handler(args: ReportArgs, history: &HistorySettings, services: &[Service]) -> String {
+    let to = args.to.unwrap_or_else(Utc::now);
+    let from = args.from.unwrap_or(to - chrono::Duration::days(30));
+    if from >= to {
+        return "--from has to be before --to".to_string();
+    }
+    let selected: Vec<&Service> = services.iter().filter(|s| args.selector.matches(s)).collect();
+    if selected.is_empty() {
+        return "No services found".to_string();
+    }
+    let records = match history::read(&history.file, None, Some(to)) {
+        Ok(records) => records,
+        Err(e) => return format!("Unable to read the history: {}", e),
+    };
+    let reports: Vec<Report> = selected
+        .iter()
+        .map(|s| report::report(&s.name, &records, s.interval, from, to, args.partial.unwrap_or(s.partial_success)))
+        .collect();
+    if args.format == "csv" {
+        return report::to_csv(&reports);
+    }
+    serde_json::to_string_pretty(&reports).unwrap_or("Failed to parse as JSON".to_string())
+}
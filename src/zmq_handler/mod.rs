@@ -1,8 +1,16 @@
-use std::sync::{Arc, Mutex};
-use zmq::{Context, Socket, ROUTER};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use zmq::{Context, Socket, REP, ROUTER};
 
+use crate::incidents::Incidents;
 use crate::settings::Settings;
+use crate::zmq_handler::curve::{zap_handler, ZAP_DOMAIN};
+use crate::zmq_handler::endpoints::{ipc_path, remove_stale_socket, set_socket_permissions, SocketFiles};
 
+pub mod access;
+mod audit;
+pub mod curve;
+mod endpoints;
 mod listen;
 mod speaker;
 
@@ -12,43 +20,96 @@ pub struct ZmqHandler {
     router: Socket,
     /// The application settings, wrapped in an Arc and Mutex for thread safety.
     settings: Arc<Mutex<Settings>>,
+    /// The incidents of the services, kept apart from the settings as they are saved after every change.
+    incidents: Arc<Mutex<Incidents>>,
+    /// Notified when a service is changed, so its test loop doesn't wait for the next run when asked to run.
+    wakeup: Arc<Condvar>,
+    /// The IPC socket files of the endpoints, removed once the handler is dropped.
+    _socket_files: SocketFiles,
 }
 
 impl ZmqHandler {
     /// Constructs a new `ZmqHandler`.
     ///
     /// This function takes the application settings as a parameter, creates a new ZeroMQ context and a ROUTER socket,
-    /// and binds the socket to the specified protocol and port from the settings.
+    /// and binds the socket to the endpoints from the settings. Stale IPC socket files are replaced, and removed again when the handler is dropped or the daemon stopped.
     ///
     /// # Arguments
     ///
     /// * `settings` - An Arc<Mutex<Settings>> that contains the application settings.
+    /// * `incidents` - An Arc<Mutex<Incidents>> that contains the incidents.
+    /// * `wakeup` - Notified when a service is changed, used along with the settings mutex.
     ///
     /// # Returns
     ///
-    /// * A new `ZmqHandler` with the created socket and the provided settings.
-    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
+    /// * A new `ZmqHandler` with the created socket and the provided settings, or why the socket couldn't be set up.
+    pub fn new(
+        settings: Arc<Mutex<Settings>>,
+        incidents: Arc<Mutex<Incidents>>,
+        wakeup: Arc<Condvar>,
+    ) -> Result<Self, String> {
         // Extract the protocol and port from the settings
-        let (protocol, port) = {
-            let settings = settings.lock().unwrap().clone();
-            (settings.protocol, settings.port)
+        let (endpoints, ipc_permissions, curve) = {
+            let settings = settings.lock().unwrap_or_else(PoisonError::into_inner).clone();
+            (settings.bind_endpoints(), settings.ipc_permissions, settings.curve)
         };
 
         // Create a new ZeroMQ context
         let context = Context::new();
 
         // Create a new ZeroMQ ROUTER socket
-        let socket = context.socket(ROUTER).unwrap();
+        let socket = context
+            .socket(ROUTER)
+            .map_err(|e| format!("Unable to create socket: {}", e))?;
 
-        // Bind the socket to the specified protocol and port
-        socket
-            .bind(&*format!("{}://*:{}", protocol, port))
-            .expect("Unable to bind socket");
+        // Only let authenticated clients in, the ZAP handler has to be running before binding
+        if let Some(curve) = curve {
+            let secret_key =
+                zmq::z85_decode(&curve.secret_key).map_err(|_| "CURVE secret key should be valid z85".to_string())?;
+            let zap = context
+                .socket(REP)
+                .and_then(|zap| zap.bind("inproc://zeromq.zap.01").map(|_| zap))
+                .map_err(|e| format!("Unable to bind ZAP handler: {}", e))?;
+            thread::Builder::new()
+                .name("ZAP".to_string())
+                .spawn(move || zap_handler(zap, curve.allowed_clients))
+                .map_err(|e| format!("Unable to spawn ZAP handler thread: {}", e))?;
+
+            socket
+                .set_curve_server(true)
+                .and_then(|_| socket.set_curve_secretkey(&secret_key))
+                .and_then(|_| socket.set_zap_domain(ZAP_DOMAIN))
+                .map_err(|e| format!("Unable to enable CURVE: {}", e))?;
+        }
+
+        // Bind the socket to every endpoint
+        let mut socket_files = SocketFiles::default();
+        for endpoint in &endpoints {
+            let ipc_path = ipc_path(endpoint);
+            if let Some(path) = ipc_path {
+                remove_stale_socket(path).map_err(|e| format!("Unable to bind {}: {}", endpoint, e))?;
+            }
+            socket
+                .bind(endpoint)
+                .map_err(|e| format!("Unable to bind {}: {}", endpoint, e))?;
+            if let Some(path) = ipc_path {
+                socket_files.push(path);
+                if let Some(mode) = ipc_permissions {
+                    set_socket_permissions(path, mode)
+                        .map_err(|e| format!("Unable to set the permissions of {}: {}", path, e))?;
+                }
+            }
+            println!("Listening on {}", endpoint);
+        }
+        socket_files.remove_on_exit();
 
         // Return a new ZmqHandler with the created socket and the provided settings
-        ZmqHandler {
+        Ok(ZmqHandler {
             router: socket,
             settings,
-        }
+            incidents,
+            wakeup,
+            _socket_files: socket_files,
+        })
     }
 }